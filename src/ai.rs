@@ -1,14 +1,60 @@
-use crate::{game::Game, move_towards, mut_two, object::Object, Tcod};
+use rand::Rng;
+use tcod::{colors::RED, map::Map as FovMap};
 
-pub fn ai_take_turn(monster_id: usize, tcod: &Tcod, game: &mut Game, objects: &mut Vec<Object>) {
+use crate::{
+    components::Ai, game::Game, move_by, mut_two, object::Object, pathfinding::move_astar,
+};
+
+pub fn ai_take_turn(monster_id: usize, fov: &FovMap, game: &mut Game, objects: &mut Vec<Object>) {
+    use Ai::*;
+    if let Some(ai) = objects[monster_id].ai.take() {
+        let new_ai = match ai {
+            Basic => ai_basic(monster_id, fov, game, objects),
+            Confused {
+                previous_ai,
+                num_turns,
+            } => ai_confused(monster_id, game, objects, previous_ai, num_turns),
+        };
+        objects[monster_id].ai = Some(new_ai);
+    }
+}
+
+fn ai_basic(monster_id: usize, fov: &FovMap, game: &mut Game, objects: &mut Vec<Object>) -> Ai {
     let (monster_x, monster_y) = objects[monster_id].pos();
-    if tcod.fov.is_in_fov(monster_x, monster_y) {
+    if fov.is_in_fov(monster_x, monster_y) {
         if objects[monster_id].distance_to(&objects[0]) >= 2.0 {
             let (player_x, player_y) = objects[0].pos();
-            move_towards(monster_id, player_x, player_y, game, objects)
+            move_astar(monster_id, player_x, player_y, game, objects)
         } else if objects[0].fighter.map_or(false, |f| f.hp > 0) {
             let (monster, player) = mut_two(monster_id, 0, objects);
             monster.attack(player, game);
         }
     }
+    Ai::Basic
+}
+
+/// Stumbles around at random until the confusion wears off, then goes back
+/// to whatever the monster was doing before.
+fn ai_confused(
+    monster_id: usize,
+    game: &mut Game,
+    objects: &mut Vec<Object>,
+    previous_ai: Box<Ai>,
+    num_turns: i32,
+) -> Ai {
+    if num_turns > 0 {
+        let dx = game.rng.gen_range(-1, 2);
+        let dy = game.rng.gen_range(-1, 2);
+        move_by(monster_id, dx, dy, game, objects);
+        Ai::Confused {
+            previous_ai: previous_ai,
+            num_turns: num_turns - 1,
+        }
+    } else {
+        game.messages.add(
+            format!("The {} is no longer confused!", objects[monster_id].name),
+            RED,
+        );
+        *previous_ai
+    }
 }
@@ -0,0 +1,128 @@
+use serde::{Deserialize, Serialize};
+use tcod::map::Map as FovMap;
+
+use crate::{
+    close_door,
+    components::{Stairs, Stat, UseResult},
+    drop_item, dungeon,
+    game::Game,
+    inventory::use_item,
+    level_up,
+    object::Object,
+    pick_item_up, player_move_or_attack, PlayerAction, ACTION_COST, DROP_COST, DUNGEON_DEPTH,
+    PICK_UP_COST, USE_ITEM_COST,
+};
+
+/// Something the player does in the game. The keyboard turns key presses
+/// into commands, but anything else that plays the game can send them too.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum Command {
+    /// Steps by (dx, dy), attacking whatever is in the way.
+    Move(i32, i32),
+    Wait,
+    /// Picks up an item from under the player.
+    PickUp,
+    /// Uses the item in an inventory slot, on `target` for items that need
+    /// one.
+    UseItem {
+        slot: usize,
+        target: Option<(i32, i32)>,
+    },
+    /// Drops the item in an inventory slot.
+    Drop(usize),
+    /// Takes the stairs down, if the player is on them.
+    Descend,
+    /// Takes the stairs up, if the player is on them.
+    Ascend,
+    /// Closes the door at (dx, dy) from the player.
+    CloseDoor(i32, i32),
+    /// Raises a stat, once the player has the experience to level up.
+    LevelUp(Stat),
+}
+
+/// Carries out `command` for the player. Returns whether it took a turn, or
+/// `PlayerAction::Won` if it took the player out of the dungeon. Commands
+/// that make no sense where the player is, such as using an empty slot, do
+/// nothing.
+pub fn execute(
+    command: Command,
+    fov: &mut FovMap,
+    game: &mut Game,
+    objects: &mut Vec<Object>,
+) -> PlayerAction {
+    if !objects[0].is_alive {
+        return PlayerAction::DidntTakeTurn;
+    }
+    match command {
+        Command::Move(dx, dy) => {
+            if (dx, dy) == (0, 0) || dx.abs() > 1 || dy.abs() > 1 {
+                return PlayerAction::DidntTakeTurn;
+            }
+            player_move_or_attack(0, dx, dy, game, objects);
+            PlayerAction::TookTurn(ACTION_COST)
+        }
+        Command::Wait => PlayerAction::TookTurn(ACTION_COST),
+        Command::PickUp => {
+            let item_id = objects
+                .iter()
+                .position(|object| object.pos() == objects[0].pos() && object.item.is_some());
+            match item_id {
+                Some(item_id) if pick_item_up(item_id, game, objects) => {
+                    PlayerAction::TookTurn(PICK_UP_COST)
+                }
+                _ => PlayerAction::DidntTakeTurn,
+            }
+        }
+        Command::UseItem { slot, target } => {
+            if slot < game.inventory.len()
+                && use_item(slot, target, fov, game, objects) != UseResult::Cancelled
+            {
+                PlayerAction::TookTurn(USE_ITEM_COST)
+            } else {
+                PlayerAction::DidntTakeTurn
+            }
+        }
+        Command::Drop(slot) => {
+            if slot < game.inventory.len() {
+                drop_item(slot, game, objects);
+                PlayerAction::TookTurn(DROP_COST)
+            } else {
+                PlayerAction::DidntTakeTurn
+            }
+        }
+        Command::Descend => {
+            if dungeon::stairs_under_player(objects) == Some(Stairs::Down) {
+                if game.dungeon_level == DUNGEON_DEPTH {
+                    // the stairs on the deepest level lead out of the dungeon
+                    return PlayerAction::Won;
+                }
+                let level = game.dungeon_level + 1;
+                dungeon::change_level(fov, game, objects, level);
+                PlayerAction::TookTurn(ACTION_COST)
+            } else {
+                PlayerAction::DidntTakeTurn
+            }
+        }
+        Command::Ascend => {
+            if dungeon::stairs_under_player(objects) == Some(Stairs::Up) {
+                let level = game.dungeon_level - 1;
+                dungeon::change_level(fov, game, objects, level);
+                PlayerAction::TookTurn(ACTION_COST)
+            } else {
+                PlayerAction::DidntTakeTurn
+            }
+        }
+        Command::CloseDoor(dx, dy) => {
+            let (x, y) = objects[0].pos();
+            if dx.abs() + dy.abs() == 1 && close_door(x + dx, y + dy, game, objects) {
+                PlayerAction::TookTurn(ACTION_COST)
+            } else {
+                PlayerAction::DidntTakeTurn
+            }
+        }
+        Command::LevelUp(stat) => {
+            level_up(stat, game, objects);
+            PlayerAction::DidntTakeTurn
+        }
+    }
+}
@@ -1,27 +1,300 @@
-use tcod::colors::{DARK_RED, ORANGE, RED};
+use std::fmt;
 
-use crate::{game::Game, object::Object};
+use serde::{Deserialize, Serialize};
+use tcod::{
+    colors::{DARK_RED, LIGHT_BLUE, LIGHT_GREEN, LIGHT_VIOLET, ORANGE, RED},
+    map::Map as FovMap,
+};
 
-#[derive(Clone, Copy, Debug, PartialEq)]
+use crate::{
+    game::Game, object::Object, targeting::closest_monster, CONFUSE_NUM_TURNS, CONFUSE_RANGE,
+    FIREBALL_DAMAGE, FIREBALL_RADIUS, HEAL_AMOUNT, LIGHTNING_DAMAGE, LIGHTNING_RANGE,
+};
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
 pub struct Fighter {
-    pub max_hp: i32,
+    /// Stats before equipment; see `Object::max_hp`, `defense` and `power`.
+    pub base_max_hp: i32,
     pub hp: i32,
-    pub defense: i32,
-    pub power: i32,
+    pub base_defense: i32,
+    pub base_power: i32,
+    /// Energy gained per tick; `NORMAL_SPEED` acts once per normal turn.
+    pub speed: i32,
+    /// Experience gathered, for the player, or given for killing a monster.
+    pub xp: i32,
     pub on_death: DeathCallback,
 }
 
-#[derive(Clone, Debug, PartialEq)]
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub enum Ai {
     Basic,
+    /// Wanders at random for `num_turns` turns, then reverts to `previous_ai`.
+    Confused {
+        previous_ai: Box<Ai>,
+        num_turns: i32,
+    },
 }
 
-#[derive(Clone, Debug, PartialEq)]
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub enum Item {
     Heal,
+    Lightning,
+    Confuse,
+    Fireball,
+    /// Equips or unequips the item's `Equipment`.
+    Equipment,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum Slot {
+    MainHand,
+    OffHand,
+    Body,
+    Head,
+    Ring,
+}
+
+impl fmt::Display for Slot {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            Slot::MainHand => "main hand",
+            Slot::OffHand => "off hand",
+            Slot::Body => "body",
+            Slot::Head => "head",
+            Slot::Ring => "ring",
+        };
+        f.write_str(name)
+    }
+}
+
+/// Something the player can wear or wield, and what it adds while it is.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Equipment {
+    pub slot: Slot,
+    pub equipped: bool,
+    pub power_bonus: i32,
+    pub defense_bonus: i32,
+    pub max_hp_bonus: i32,
+}
+
+impl Equipment {
+    pub fn new(slot: Slot, power_bonus: i32, defense_bonus: i32, max_hp_bonus: i32) -> Self {
+        Equipment {
+            slot: slot,
+            equipped: false,
+            power_bonus: power_bonus,
+            defense_bonus: defense_bonus,
+            max_hp_bonus: max_hp_bonus,
+        }
+    }
+}
+
+/// A stat the player can raise on levelling up.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum Stat {
+    Constitution,
+    Strength,
+    Agility,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum Stairs {
+    Up,
+    Down,
+}
+
+/// What using an item does: its inventory slot, the target picked for it
+/// and what the player can see decide the outcome.
+type UseFn = fn(usize, Option<(i32, i32)>, &FovMap, &mut Game, &mut Vec<Object>) -> UseResult;
+
+impl Item {
+    /// Uses the item, on `target` for items that need one.
+    pub fn on_use(
+        &self,
+        inventory_id: usize,
+        target: Option<(i32, i32)>,
+        fov: &FovMap,
+        game: &mut Game,
+        objects: &mut Vec<Object>,
+    ) -> UseResult {
+        use Item::*;
+        let on_use: UseFn = match self {
+            Heal => cast_heal,
+            Lightning => cast_lightning,
+            Confuse => cast_confuse,
+            Fireball => cast_fireball,
+            Equipment => toggle_equipment,
+        };
+        on_use(inventory_id, target, fov, game, objects)
+    }
 }
 
 #[derive(Clone, Copy, Debug, PartialEq)]
+pub enum UseResult {
+    UsedUp,
+    /// Used, but stays in the inventory, like equipment.
+    UsedAndKept,
+    Cancelled,
+}
+
+fn cast_heal(
+    _inventory_id: usize,
+    _target: Option<(i32, i32)>,
+    _fov: &FovMap,
+    game: &mut Game,
+    objects: &mut Vec<Object>,
+) -> UseResult {
+    // heal the player
+    if let Some(fighter) = objects[0].fighter {
+        if fighter.hp == objects[0].max_hp() {
+            game.messages.add("You are already at full health.", RED);
+            return UseResult::Cancelled;
+        }
+        game.messages
+            .add("Your wounds start to feel better!", LIGHT_VIOLET);
+        objects[0].heal(HEAL_AMOUNT);
+        return UseResult::UsedUp;
+    }
+    UseResult::Cancelled
+}
+
+fn cast_lightning(
+    _inventory_id: usize,
+    _target: Option<(i32, i32)>,
+    fov: &FovMap,
+    game: &mut Game,
+    objects: &mut Vec<Object>,
+) -> UseResult {
+    // find the closest enemy (inside a maximum range) and damage it
+    let monster_id = closest_monster(fov, objects, LIGHTNING_RANGE as f32);
+    if let Some(monster_id) = monster_id {
+        game.messages.add(
+            format!(
+                "A lightning bolt strikes the {} with a loud thunder! \
+                 The damage is {} hit points.",
+                objects[monster_id].name, LIGHTNING_DAMAGE
+            ),
+            LIGHT_BLUE,
+        );
+        if let Some(xp) = objects[monster_id].take_damage(LIGHTNING_DAMAGE, game) {
+            objects[0].fighter.as_mut().unwrap().xp += xp;
+        }
+        UseResult::UsedUp
+    } else {
+        game.messages
+            .add("No enemy is close enough to strike.", RED);
+        UseResult::Cancelled
+    }
+}
+
+fn cast_confuse(
+    _inventory_id: usize,
+    target: Option<(i32, i32)>,
+    fov: &FovMap,
+    game: &mut Game,
+    objects: &mut Vec<Object>,
+) -> UseResult {
+    // confuse the monster on the target tile, if it is in sight and range
+    let monster_id = target.and_then(|(x, y)| {
+        (1..objects.len()).find(|&id| {
+            objects[id].pos() == (x, y)
+                && objects[id].fighter.is_some()
+                && fov.is_in_fov(x, y)
+                && objects[0].distance(x, y) <= CONFUSE_RANGE as f32
+        })
+    });
+    if let Some(monster_id) = monster_id {
+        // replace the monster's AI with a confused one; it gets the old
+        // one back after some turns
+        let old_ai = objects[monster_id].ai.take().unwrap_or(Ai::Basic);
+        objects[monster_id].ai = Some(Ai::Confused {
+            previous_ai: Box::new(old_ai),
+            num_turns: CONFUSE_NUM_TURNS,
+        });
+        game.messages.add(
+            format!(
+                "The eyes of the {} look vacant, as it starts to stumble around!",
+                objects[monster_id].name
+            ),
+            LIGHT_GREEN,
+        );
+        UseResult::UsedUp
+    } else {
+        UseResult::Cancelled
+    }
+}
+
+fn cast_fireball(
+    _inventory_id: usize,
+    target: Option<(i32, i32)>,
+    fov: &FovMap,
+    game: &mut Game,
+    objects: &mut Vec<Object>,
+) -> UseResult {
+    // the fireball explodes on the target tile, which must be in sight
+    let (x, y) = match target {
+        Some((x, y)) if fov.is_in_fov(x, y) => (x, y),
+        _ => return UseResult::Cancelled,
+    };
+    game.messages.add(
+        format!(
+            "The fireball explodes, burning everything within {} tiles!",
+            FIREBALL_RADIUS
+        ),
+        ORANGE,
+    );
+    let mut xp_to_gain = 0;
+    for (id, object) in objects.iter_mut().enumerate() {
+        if object.distance(x, y) <= FIREBALL_RADIUS as f32 && object.fighter.is_some() {
+            game.messages.add(
+                format!(
+                    "The {} gets burned for {} hit points.",
+                    object.name, FIREBALL_DAMAGE
+                ),
+                ORANGE,
+            );
+            if let Some(xp) = object.take_damage(FIREBALL_DAMAGE, game) {
+                // no experience for burning yourself
+                if id != 0 {
+                    xp_to_gain += xp;
+                }
+            }
+        }
+    }
+    if let Some(fighter) = objects[0].fighter.as_mut() {
+        fighter.xp += xp_to_gain;
+    }
+    UseResult::UsedUp
+}
+
+fn toggle_equipment(
+    inventory_id: usize,
+    _target: Option<(i32, i32)>,
+    _fov: &FovMap,
+    game: &mut Game,
+    objects: &mut Vec<Object>,
+) -> UseResult {
+    let equipment = match game.inventory[inventory_id].equipment {
+        Some(equipment) => equipment,
+        None => return UseResult::Cancelled,
+    };
+    if equipment.equipped {
+        game.inventory[inventory_id].unequip(&mut objects[0], &mut game.messages);
+    } else {
+        // take off whatever already takes up the slot
+        let current = game.inventory.iter().position(|item| {
+            item.equipment
+                .map_or(false, |e| e.equipped && e.slot == equipment.slot)
+        });
+        if let Some(current) = current {
+            game.inventory[current].unequip(&mut objects[0], &mut game.messages);
+        }
+        game.inventory[inventory_id].equip(&mut objects[0], &mut game.messages);
+    }
+    UseResult::UsedAndKept
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
 pub enum DeathCallback {
     Player,
     Monster,
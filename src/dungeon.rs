@@ -0,0 +1,83 @@
+use std::mem;
+
+use serde::{Deserialize, Serialize};
+use tcod::{
+    colors::{LIGHT_VIOLET, VIOLET},
+    map::Map as FovMap,
+};
+
+use crate::{
+    components::Stairs, game::Game, initialise_fov, make_map, object::Object, Map, FOV_ALGORITHM,
+    FOV_LIGHT_WALLS, TORCH_RADIUS,
+};
+
+/// A level the player has left, kept so it can be restored as it was.
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+pub struct Level {
+    pub map: Map,
+    pub objects: Vec<Object>,
+}
+
+/// Moves the player to `new_level`, generating it on the first visit, and
+/// rebuilds the FOV map for it.
+pub fn change_level(fov: &mut FovMap, game: &mut Game, objects: &mut Vec<Object>, new_level: u32) {
+    if new_level > game.dungeon_level {
+        game.messages.add(
+            "You descend deeper into the heart of the dungeon...",
+            VIOLET,
+        );
+    } else {
+        game.messages
+            .add("You climb back up the stairs.", LIGHT_VIOLET);
+    }
+    enter_level(game, objects, new_level);
+
+    initialise_fov(fov, &game.map);
+    let player = &objects[0];
+    fov.compute_fov(
+        player.x,
+        player.y,
+        TORCH_RADIUS,
+        FOV_LIGHT_WALLS,
+        FOV_ALGORITHM,
+    );
+}
+
+/// Stashes the current level and swaps in `new_level`. The player stays at
+/// index 0 and is placed on the stairs leading back to where they came from.
+pub fn enter_level(game: &mut Game, objects: &mut Vec<Object>, new_level: u32) {
+    let going_down = new_level > game.dungeon_level;
+    let current = Level {
+        map: mem::take(&mut game.map),
+        objects: objects.drain(1..).collect(),
+    };
+    game.levels.insert(game.dungeon_level, current);
+    game.dungeon_level = new_level;
+
+    match game.levels.remove(&new_level) {
+        Some(level) => {
+            game.map = level.map;
+            objects.extend(level.objects);
+            let arrival = if going_down { Stairs::Up } else { Stairs::Down };
+            let stairs = objects
+                .iter()
+                .find(|object| object.stairs == Some(arrival))
+                .map(|object| object.pos());
+            if let Some((x, y)) = stairs {
+                objects[0].set_pos(x, y);
+            }
+        }
+        None => {
+            game.map = make_map(objects, new_level, &mut game.rng);
+        }
+    }
+}
+
+/// Returns the stairs the player is standing on, if any.
+pub fn stairs_under_player(objects: &[Object]) -> Option<Stairs> {
+    objects
+        .iter()
+        .skip(1)
+        .find(|object| object.pos() == objects[0].pos() && object.stairs.is_some())
+        .and_then(|object| object.stairs)
+}
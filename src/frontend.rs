@@ -0,0 +1,132 @@
+use tcod::{
+    console::{FontLayout, FontType, Offscreen, Root},
+    input::{self, Event, KeyCode, Mouse},
+};
+
+use crate::{
+    render::{Canvas, Layer, Screen},
+    FPS, MAP_HEIGHT, MAP_WIDTH, PANEL_HEIGHT, SCREEN_HEIGHT, SCREEN_WIDTH,
+};
+
+/// A key press, independent of where it was read from.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct KeyPress {
+    pub code: KeyCode,
+    /// The character typed, for `KeyCode::Text` and `KeyCode::Char` keys.
+    pub printable: char,
+    pub alt: bool,
+    pub ctrl: bool,
+    pub shift: bool,
+}
+
+impl KeyPress {
+    pub fn text(printable: char) -> Self {
+        KeyPress {
+            code: KeyCode::Text,
+            printable: printable,
+            ..Default::default()
+        }
+    }
+
+    pub fn code(code: KeyCode) -> Self {
+        KeyPress {
+            code: code,
+            ..Default::default()
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub enum Input {
+    Key(KeyPress),
+    Mouse(Mouse),
+}
+
+/// Where frames are shown and input comes from. The game logic only talks
+/// to this trait, so the libtcod window and the terminal share all of it.
+pub trait Frontend {
+    type Root: Canvas;
+    type Layer: Layer<Self::Root>;
+
+    fn screen(&mut self) -> &mut Screen<Self::Root, Self::Layer>;
+    /// Shows everything drawn on the root since the last flush.
+    fn flush(&mut self);
+    fn wait_for_keypress(&mut self) -> KeyPress;
+    /// Returns the next pending input, if any, without waiting for one.
+    fn check_for_event(&mut self) -> Option<Input>;
+    fn window_closed(&self) -> bool;
+    fn toggle_fullscreen(&mut self);
+}
+
+pub struct TcodFrontend {
+    screen: Screen<Root, Offscreen>,
+}
+
+impl TcodFrontend {
+    pub fn new() -> Self {
+        let root = Root::initializer()
+            .font("./resources/arial10x10.png", FontLayout::Tcod)
+            .font_type(FontType::Greyscale)
+            .size(SCREEN_WIDTH, SCREEN_HEIGHT)
+            .title("Tutorial")
+            .init();
+        tcod::system::set_fps(FPS);
+
+        TcodFrontend {
+            screen: Screen {
+                root: root,
+                con: Offscreen::new(MAP_WIDTH, MAP_HEIGHT),
+                panel: Offscreen::new(SCREEN_WIDTH, PANEL_HEIGHT),
+            },
+        }
+    }
+}
+
+fn key_press(key: input::Key) -> KeyPress {
+    let printable = if key.code == KeyCode::Text {
+        key.text().chars().next().unwrap_or('\0')
+    } else {
+        key.printable
+    };
+    KeyPress {
+        code: key.code,
+        printable: printable,
+        alt: key.alt,
+        ctrl: key.ctrl,
+        shift: key.shift,
+    }
+}
+
+impl Frontend for TcodFrontend {
+    type Root = Root;
+    type Layer = Offscreen;
+
+    fn screen(&mut self) -> &mut Screen<Root, Offscreen> {
+        &mut self.screen
+    }
+
+    fn flush(&mut self) {
+        self.screen.root.flush();
+    }
+
+    fn wait_for_keypress(&mut self) -> KeyPress {
+        key_press(self.screen.root.wait_for_keypress(true))
+    }
+
+    fn check_for_event(&mut self) -> Option<Input> {
+        match input::check_for_event(input::MOUSE | input::KEY_PRESS) {
+            Some((_, Event::Mouse(m))) => Some(Input::Mouse(m)),
+            Some((_, Event::Key(k))) => Some(Input::Key(key_press(k))),
+            _ => None,
+        }
+    }
+
+    fn window_closed(&self) -> bool {
+        self.screen.root.window_closed()
+    }
+
+    fn toggle_fullscreen(&mut self) {
+        let fullscreen = self.screen.root.is_fullscreen();
+        self.screen.root.set_fullscreen(!fullscreen);
+    }
+}
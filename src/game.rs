@@ -1,7 +1,20 @@
-use crate::{messages::Messages, object::Object, Map};
+use std::collections::HashMap;
 
+use serde::{Deserialize, Serialize};
+
+use crate::{dungeon::Level, messages::Messages, object::Object, rng::GameRng, Map};
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
 pub struct Game {
     pub map: Map,
     pub messages: Messages,
     pub inventory: Vec<Object>,
+    pub rng: GameRng,
+    pub dungeon_level: u32,
+    /// Levels the player has left, by depth, waiting to be revisited.
+    pub levels: HashMap<u32, Level>,
+    /// Tiles that changed since the FOV map was last updated, such as doors
+    /// opened or closed.
+    #[serde(skip)]
+    pub changed_tiles: Vec<(i32, i32)>,
 }
@@ -0,0 +1,165 @@
+use tcod::{
+    colors::{BLACK, LIGHT_GREY, LIGHT_YELLOW, WHITE},
+    input::KeyCode,
+    TextAlignment,
+};
+
+use crate::{
+    frontend::{Frontend, KeyPress},
+    messages::{Message, Messages},
+    render::Canvas,
+    Tcod, SCREEN_HEIGHT, SCREEN_WIDTH,
+};
+
+/// Rows above the log, for the title and the search line.
+const HEADER_HEIGHT: i32 = 2;
+/// Columns left of each message, for its turn number.
+const STAMP_WIDTH: i32 = 7;
+const LOG_WIDTH: i32 = SCREEN_WIDTH - STAMP_WIDTH;
+
+/// Where the history screen is scrolled to and what it is filtered by.
+#[derive(Debug, Default)]
+pub struct HistoryView {
+    /// How many of the shown messages are hidden below the bottom row, so
+    /// 0 shows the newest ones.
+    pub scroll: usize,
+    /// Only messages containing this are shown.
+    pub search: String,
+    /// Whether keys go into the search rather than scroll the log.
+    pub typing: bool,
+}
+
+/// Shows the whole message log until the player presses Escape.
+pub fn show_history<F: Frontend>(tcod: &mut Tcod<F>, messages: &Messages) {
+    let mut view = HistoryView::default();
+    loop {
+        render_history(&view, messages, &mut tcod.frontend.screen().root);
+        tcod.frontend.flush();
+        if tcod.frontend.window_closed() {
+            return;
+        }
+        let key = tcod.frontend.wait_for_keypress();
+        if !view.handle_key(key, messages, &tcod.frontend.screen().root) {
+            return;
+        }
+    }
+}
+
+/// How many of `messages`, laid out one after another from the edge of the
+/// log, fit on the screen.
+fn fitting<'a, C, I>(canvas: &C, messages: I) -> usize
+where
+    C: Canvas,
+    I: Iterator<Item = &'a Message>,
+{
+    let mut rows = SCREEN_HEIGHT - HEADER_HEIGHT;
+    let mut count = 0;
+    for message in messages {
+        rows -= canvas.get_height_rect(0, 0, LOG_WIDTH, 0, &message.line());
+        if rows < 0 {
+            break;
+        }
+        count += 1;
+    }
+    count
+}
+
+impl HistoryView {
+    /// Scrolls, pages or edits the search. Returns false once the screen
+    /// should close.
+    pub fn handle_key<C: Canvas>(
+        &mut self,
+        key: KeyPress,
+        messages: &Messages,
+        canvas: &C,
+    ) -> bool {
+        if self.typing {
+            match key.code {
+                KeyCode::Enter | KeyCode::NumPadEnter => self.typing = false,
+                KeyCode::Escape => {
+                    self.typing = false;
+                    self.search.clear();
+                }
+                KeyCode::Backspace => {
+                    self.search.pop();
+                }
+                KeyCode::Text | KeyCode::Char if key.printable != '\0' => {
+                    self.search.push(key.printable)
+                }
+                _ => {}
+            }
+            self.scroll = 0;
+            return true;
+        }
+
+        let shown = messages.matching(&self.search);
+        // scrolling stops once the oldest message is at the top
+        let oldest_page = fitting(canvas, shown.iter().copied());
+        let max_scroll = shown.len() - oldest_page;
+        let page = fitting(canvas, shown.iter().rev().skip(self.scroll).copied()).max(1);
+        self.scroll = match key.code {
+            KeyCode::Escape => return false,
+            KeyCode::Up => self.scroll + 1,
+            KeyCode::Down => self.scroll.saturating_sub(1),
+            KeyCode::PageUp => self.scroll + page,
+            KeyCode::PageDown => self.scroll.saturating_sub(page),
+            KeyCode::Home => max_scroll,
+            KeyCode::End => 0,
+            KeyCode::Text if key.printable == '/' => {
+                self.typing = true;
+                self.search.clear();
+                0
+            }
+            _ => self.scroll,
+        }
+        .min(max_scroll);
+        true
+    }
+}
+
+/// Draws the messages matching the view's search over the whole root, each
+/// in its own colour and next to the turn it was logged on.
+pub fn render_history<C: Canvas>(view: &HistoryView, messages: &Messages, root: &mut C) {
+    root.clear(BLACK);
+    let shown = messages.matching(&view.search);
+    let title = if view.search.is_empty() {
+        format!("Message log: {} messages", shown.len())
+    } else {
+        format!(
+            "Message log: {} of {} messages match",
+            shown.len(),
+            messages.len()
+        )
+    };
+    root.print(0, 0, TextAlignment::Left, LIGHT_YELLOW, &title);
+    if view.scroll > 0 {
+        let newer = format!("{} newer below", view.scroll);
+        root.print(
+            SCREEN_WIDTH - 1,
+            0,
+            TextAlignment::Right,
+            LIGHT_GREY,
+            &newer,
+        );
+    }
+    let help = if view.typing {
+        format!("Search: {}_", view.search)
+    } else if !view.search.is_empty() {
+        format!("Search: {} (/ to change, Escape to close)", view.search)
+    } else {
+        "Up/Down and PgUp/PgDn scroll, Home/End jump, / searches, Escape closes.".to_string()
+    };
+    root.print(0, 1, TextAlignment::Left, WHITE, &help);
+
+    let mut y = SCREEN_HEIGHT;
+    for message in shown.iter().rev().skip(view.scroll) {
+        let line = message.line();
+        y -= root.get_height_rect(STAMP_WIDTH, 0, LOG_WIDTH, 0, &line);
+        if y < HEADER_HEIGHT {
+            break;
+        }
+        let stamp = format!("{:>5}", message.turn);
+        root.print(0, y, TextAlignment::Left, LIGHT_GREY, &stamp);
+        root.print_rect(STAMP_WIDTH, y, LOG_WIDTH, 0, message.colour, &line);
+    }
+}
@@ -1,53 +1,65 @@
 use tcod::{
-    colors::WHITE,
-    console::{blit, Offscreen, Root},
-    BackgroundFlag, Console,
+    colors::{LIGHT_CYAN, WHITE},
+    map::Map as FovMap,
+    TextAlignment,
 };
 
-use crate::{object::Object, INVENTORY_WIDTH, SCREEN_HEIGHT, SCREEN_WIDTH};
+use crate::{
+    command::Command,
+    components::{Item, UseResult},
+    frontend::Frontend,
+    game::Game,
+    object::Object,
+    render::{Canvas, Layer, Screen},
+    targeting::{target_monster, target_tile},
+    Tcod, CONFUSE_RANGE, INVENTORY_WIDTH, SCREEN_HEIGHT, SCREEN_WIDTH,
+};
 
-pub fn menu<T: AsRef<str>>(
+/// Draws a lettered list of options under a header, centred on the root.
+pub fn render_menu<R: Canvas, L: Layer<R>, T: AsRef<str>>(
     header: &str,
     options: &[T],
     width: i32,
-    root: &mut Root,
-) -> Option<usize> {
+    screen: &mut Screen<R, L>,
+) {
     assert!(
         options.len() <= 26,
         "Cannot have a menu with more than 26 options."
     );
-    let header_height = root.get_height_rect(0, 0, width, SCREEN_HEIGHT, header);
+    let header_height = screen
+        .root
+        .get_height_rect(0, 0, width, SCREEN_HEIGHT, header);
     let height = options.len() as i32 + header_height;
-    let mut window = Offscreen::new(width, height);
+    let mut window = L::new_layer(width, height);
 
-    window.set_default_foreground(WHITE);
-    window.print_rect_ex(
-        0,
-        0,
-        width,
-        height,
-        BackgroundFlag::None,
-        tcod::TextAlignment::Left,
-        header,
-    );
+    window.print_rect(0, 0, width, height, WHITE, header);
 
     for (index, option_text) in options.iter().enumerate() {
         let menu_letter = (b'a' + index as u8) as char;
         let text = format!("({}) {}", menu_letter, option_text.as_ref());
-        window.print_ex(
+        window.print(
             0,
             header_height + index as i32,
-            tcod::BackgroundFlag::None,
-            tcod::TextAlignment::Left,
-            text,
+            TextAlignment::Left,
+            WHITE,
+            &text,
         );
     }
 
     let x = SCREEN_WIDTH / 2 - width / 2;
     let y = SCREEN_HEIGHT / 2 - height / 2;
-    tcod::console::blit(&window, (0, 0), (width, height), root, (x, y), 1.0, 0.7);
-    root.flush();
-    let key = root.wait_for_keypress(true);
+    window.blit((0, 0), (width, height), &mut screen.root, (x, y), 1.0, 0.7);
+}
+
+pub fn menu<F: Frontend, T: AsRef<str>>(
+    header: &str,
+    options: &[T],
+    width: i32,
+    tcod: &mut Tcod<F>,
+) -> Option<usize> {
+    render_menu(header, options, width, tcod.frontend.screen());
+    tcod.frontend.flush();
+    let key = tcod.frontend.wait_for_keypress();
     if key.printable.is_alphabetic() {
         let index = key.printable.to_ascii_lowercase() as usize - 'a' as usize;
         if index < options.len() {
@@ -60,16 +72,102 @@ pub fn menu<T: AsRef<str>>(
     }
 }
 
-pub fn inventory_menu(inventory: &Vec<Object>, header: &str, root: &mut Root) -> Option<usize> {
+pub fn msgbox<F: Frontend>(text: &str, width: i32, tcod: &mut Tcod<F>) {
+    let options: &[&str] = &[];
+    menu(text, options, width, tcod);
+}
+
+pub fn inventory_menu<F: Frontend>(
+    inventory: &Vec<Object>,
+    header: &str,
+    tcod: &mut Tcod<F>,
+) -> Option<usize> {
     let options = if inventory.len() == 0 {
         vec!["Inventory is empty".into()]
     } else {
-        inventory.iter().map(|item| item.name.clone()).collect()
+        inventory
+            .iter()
+            .map(|item| match item.equipment {
+                Some(equipment) if equipment.equipped => {
+                    format!("{} (on {})", item.name, equipment.slot)
+                }
+                _ => item.name.clone(),
+            })
+            .collect()
     };
-    let inventory_index = menu(header, &options, INVENTORY_WIDTH, root);
+    let inventory_index = menu(header, &options, INVENTORY_WIDTH, tcod);
     if inventory.len() > 0 {
         inventory_index
     } else {
         None
     }
 }
+
+/// Asks the player for whatever the item needs, such as a target, and
+/// returns the command that uses it, or `None` if they cancel.
+pub fn use_item_command<F: Frontend>(
+    inventory_id: usize,
+    tcod: &mut Tcod<F>,
+    game: &mut Game,
+    objects: &[Object],
+) -> Option<Command> {
+    let target = match game.inventory[inventory_id].item {
+        Some(Item::Confuse) => {
+            game.messages.add(
+                "Pick an enemy to confuse with the movement keys, Tab or the mouse, then Enter or \
+                 left-click; Escape or right-click cancels.",
+                LIGHT_CYAN,
+            );
+            let monster_id = target_monster(tcod, game, objects, Some(CONFUSE_RANGE as f32));
+            Some(monster_id.map(|id| objects[id].pos()))
+        }
+        Some(Item::Fireball) => {
+            game.messages.add(
+                "Pick a target tile for the fireball with the movement keys, Tab or the mouse, then \
+                 Enter or left-click; Escape or right-click cancels.",
+                LIGHT_CYAN,
+            );
+            Some(target_tile(tcod, game, objects, None))
+        }
+        _ => None,
+    };
+    match target {
+        Some(None) => {
+            game.messages.add("Cancelled", WHITE);
+            None
+        }
+        target => Some(Command::UseItem {
+            slot: inventory_id,
+            target: target.flatten(),
+        }),
+    }
+}
+
+pub fn use_item(
+    inventory_id: usize,
+    target: Option<(i32, i32)>,
+    fov: &FovMap,
+    game: &mut Game,
+    objects: &mut Vec<Object>,
+) -> UseResult {
+    if let Some(item) = game.inventory[inventory_id].item.clone() {
+        let result = item.on_use(inventory_id, target, fov, game, objects);
+        match result {
+            UseResult::UsedUp => {
+                // destroy after use, unless it was cancelled for some reason
+                game.inventory.remove(inventory_id);
+            }
+            UseResult::UsedAndKept => {}
+            UseResult::Cancelled => {
+                game.messages.add("Cancelled", WHITE);
+            }
+        }
+        result
+    } else {
+        game.messages.add(
+            format!("The {} cannot be used.", game.inventory[inventory_id].name),
+            WHITE,
+        );
+        UseResult::Cancelled
+    }
+}
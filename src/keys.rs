@@ -0,0 +1,407 @@
+use std::{collections::BTreeMap, error::Error, fs, io, sync::OnceLock};
+
+use serde::Deserialize;
+use tcod::input::KeyCode;
+
+use crate::frontend::KeyPress;
+
+pub const KEYS_FILE: &str = "resources/keys.json";
+
+static BINDINGS: OnceLock<Bindings> = OnceLock::new();
+
+/// What a key can be bound to.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize)]
+pub enum Action {
+    MoveUp,
+    MoveDown,
+    MoveLeft,
+    MoveRight,
+    MoveUpLeft,
+    MoveUpRight,
+    MoveDownLeft,
+    MoveDownRight,
+    Wait,
+    PickUp,
+    Inventory,
+    Drop,
+    Descend,
+    Ascend,
+    CloseDoor,
+    Look,
+    MessageLog,
+    Help,
+    ToggleFullscreen,
+    Exit,
+}
+
+impl Action {
+    /// Every action, in the order the help lists them.
+    pub const ALL: [Action; 20] = [
+        Action::MoveUp,
+        Action::MoveDown,
+        Action::MoveLeft,
+        Action::MoveRight,
+        Action::MoveUpLeft,
+        Action::MoveUpRight,
+        Action::MoveDownLeft,
+        Action::MoveDownRight,
+        Action::Wait,
+        Action::PickUp,
+        Action::Inventory,
+        Action::Drop,
+        Action::Descend,
+        Action::Ascend,
+        Action::CloseDoor,
+        Action::Look,
+        Action::MessageLog,
+        Action::Help,
+        Action::ToggleFullscreen,
+        Action::Exit,
+    ];
+
+    /// The step a movement action takes.
+    pub fn direction(self) -> Option<(i32, i32)> {
+        match self {
+            Action::MoveUp => Some((0, -1)),
+            Action::MoveDown => Some((0, 1)),
+            Action::MoveLeft => Some((-1, 0)),
+            Action::MoveRight => Some((1, 0)),
+            Action::MoveUpLeft => Some((-1, -1)),
+            Action::MoveUpRight => Some((1, -1)),
+            Action::MoveDownLeft => Some((-1, 1)),
+            Action::MoveDownRight => Some((1, 1)),
+            _ => None,
+        }
+    }
+
+    pub fn description(self) -> &'static str {
+        match self {
+            Action::MoveUp => "Move up",
+            Action::MoveDown => "Move down",
+            Action::MoveLeft => "Move left",
+            Action::MoveRight => "Move right",
+            Action::MoveUpLeft => "Move up and left",
+            Action::MoveUpRight => "Move up and right",
+            Action::MoveDownLeft => "Move down and left",
+            Action::MoveDownRight => "Move down and right",
+            Action::Wait => "Wait a turn",
+            Action::PickUp => "Pick up an item",
+            Action::Inventory => "Use an item",
+            Action::Drop => "Drop an item",
+            Action::Descend => "Go down stairs",
+            Action::Ascend => "Go up stairs",
+            Action::CloseDoor => "Close a door",
+            Action::Look => "Look around",
+            Action::MessageLog => "Message log",
+            Action::Help => "This help",
+            Action::ToggleFullscreen => "Toggle fullscreen",
+            Action::Exit => "Save and quit",
+        }
+    }
+}
+
+/// A set of default bindings that can be switched on in the keys file.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Profile {
+    /// The arrow keys, with Home, End, Page Up and Page Down for diagonals.
+    Arrows,
+    /// The number pad, with 5 to wait.
+    Numpad,
+    /// hjkl and yubn.
+    Vi,
+}
+
+impl Profile {
+    fn bindings(self) -> &'static [(&'static str, Action)] {
+        match self {
+            Profile::Arrows => &[
+                ("Up", Action::MoveUp),
+                ("Down", Action::MoveDown),
+                ("Left", Action::MoveLeft),
+                ("Right", Action::MoveRight),
+                ("Home", Action::MoveUpLeft),
+                ("PageUp", Action::MoveUpRight),
+                ("End", Action::MoveDownLeft),
+                ("PageDown", Action::MoveDownRight),
+            ],
+            Profile::Numpad => &[
+                ("NumPad8", Action::MoveUp),
+                ("NumPad2", Action::MoveDown),
+                ("NumPad4", Action::MoveLeft),
+                ("NumPad6", Action::MoveRight),
+                ("NumPad7", Action::MoveUpLeft),
+                ("NumPad9", Action::MoveUpRight),
+                ("NumPad1", Action::MoveDownLeft),
+                ("NumPad3", Action::MoveDownRight),
+                ("NumPad5", Action::Wait),
+            ],
+            Profile::Vi => &[
+                ("k", Action::MoveUp),
+                ("j", Action::MoveDown),
+                ("h", Action::MoveLeft),
+                ("l", Action::MoveRight),
+                ("y", Action::MoveUpLeft),
+                ("u", Action::MoveUpRight),
+                ("b", Action::MoveDownLeft),
+                ("n", Action::MoveDownRight),
+            ],
+        }
+    }
+}
+
+/// The keys every profile shares.
+const COMMON_BINDINGS: &[(&str, Action)] = &[
+    (".", Action::Wait),
+    ("g", Action::PickUp),
+    ("i", Action::Inventory),
+    ("d", Action::Drop),
+    (">", Action::Descend),
+    ("<", Action::Ascend),
+    ("c", Action::CloseDoor),
+    ("x", Action::Look),
+    ("m", Action::MessageLog),
+    ("?", Action::Help),
+    ("Alt+Enter", Action::ToggleFullscreen),
+    ("Escape", Action::Exit),
+];
+
+/// The keys that have names rather than being typed as a character.
+const NAMED_KEYS: &[(&str, KeyCode)] = &[
+    ("Up", KeyCode::Up),
+    ("Down", KeyCode::Down),
+    ("Left", KeyCode::Left),
+    ("Right", KeyCode::Right),
+    ("Home", KeyCode::Home),
+    ("End", KeyCode::End),
+    ("PageUp", KeyCode::PageUp),
+    ("PageDown", KeyCode::PageDown),
+    ("Insert", KeyCode::Insert),
+    ("Delete", KeyCode::Delete),
+    ("Enter", KeyCode::Enter),
+    ("Escape", KeyCode::Escape),
+    ("Tab", KeyCode::Tab),
+    ("Backspace", KeyCode::Backspace),
+    ("NumPad0", KeyCode::NumPad0),
+    ("NumPad1", KeyCode::NumPad1),
+    ("NumPad2", KeyCode::NumPad2),
+    ("NumPad3", KeyCode::NumPad3),
+    ("NumPad4", KeyCode::NumPad4),
+    ("NumPad5", KeyCode::NumPad5),
+    ("NumPad6", KeyCode::NumPad6),
+    ("NumPad7", KeyCode::NumPad7),
+    ("NumPad8", KeyCode::NumPad8),
+    ("NumPad9", KeyCode::NumPad9),
+    ("NumPadEnter", KeyCode::NumPadEnter),
+    ("F1", KeyCode::F1),
+    ("F2", KeyCode::F2),
+    ("F3", KeyCode::F3),
+    ("F4", KeyCode::F4),
+    ("F5", KeyCode::F5),
+    ("F6", KeyCode::F6),
+    ("F7", KeyCode::F7),
+    ("F8", KeyCode::F8),
+    ("F9", KeyCode::F9),
+    ("F10", KeyCode::F10),
+    ("F11", KeyCode::F11),
+    ("F12", KeyCode::F12),
+];
+
+/// A key as named in the keys file: a typed character such as "k" or ">",
+/// or a named key such as "Up" or "NumPad7", optionally after "Alt+".
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Key {
+    code: KeyCode,
+    /// The character typed, for `KeyCode::Text` keys.
+    printable: char,
+    alt: bool,
+}
+
+impl Key {
+    pub fn parse(name: &str) -> Result<Key, String> {
+        let (alt, rest) = match name.strip_prefix("Alt+") {
+            Some(rest) => (true, rest),
+            None => (false, name),
+        };
+        let rest = if rest == "Space" { " " } else { rest };
+        let mut chars = rest.chars();
+        let key = match (chars.next(), chars.next()) {
+            (Some(printable), None) => Key {
+                code: KeyCode::Text,
+                printable: printable,
+                alt: alt,
+            },
+            _ => {
+                let &(_, code) = NAMED_KEYS
+                    .iter()
+                    .find(|&&(known, _)| known == rest)
+                    .ok_or_else(|| format!("unknown key {:?}", name))?;
+                Key {
+                    code: code,
+                    printable: '\0',
+                    alt: alt,
+                }
+            }
+        };
+        Ok(key)
+    }
+
+    pub fn matches(&self, key: KeyPress) -> bool {
+        key.code == self.code
+            && key.alt == self.alt
+            && (self.code != KeyCode::Text || key.printable == self.printable)
+    }
+
+    /// The key's name, as written in the keys file.
+    pub fn name(&self) -> String {
+        let name = match self.code {
+            KeyCode::Text if self.printable == ' ' => "Space".to_string(),
+            KeyCode::Text => self.printable.to_string(),
+            code => NAMED_KEYS
+                .iter()
+                .find(|&&(_, known)| known == code)
+                .map_or("?", |&(name, _)| name)
+                .to_string(),
+        };
+        if self.alt {
+            format!("Alt+{}", name)
+        } else {
+            name
+        }
+    }
+}
+
+/// Which key does what.
+#[derive(Debug)]
+pub struct Bindings {
+    bindings: Vec<(Key, Action)>,
+}
+
+impl Bindings {
+    /// The common keys plus those of each profile.
+    pub fn from_profiles(profiles: &[Profile]) -> Self {
+        let mut bindings = Bindings { bindings: vec![] };
+        let defaults = profiles.iter().flat_map(|profile| profile.bindings());
+        for &(name, action) in COMMON_BINDINGS.iter().chain(defaults) {
+            let key = Key::parse(name).expect("the default bindings use known keys");
+            bindings.bind(key, action);
+        }
+        bindings
+    }
+
+    /// Makes `key` do `action`, instead of whatever it did before.
+    pub fn bind(&mut self, key: Key, action: Action) {
+        self.bindings.retain(|&(bound, _)| bound != key);
+        self.bindings.push((key, action));
+    }
+
+    pub fn action_for(&self, key: KeyPress) -> Option<Action> {
+        self.bindings
+            .iter()
+            .find(|(bound, _)| bound.matches(key))
+            .map(|&(_, action)| action)
+    }
+
+    /// The keys bound to `action`.
+    pub fn keys_for(&self, action: Action) -> Vec<Key> {
+        self.bindings
+            .iter()
+            .filter(|&&(_, bound)| bound == action)
+            .map(|&(key, _)| key)
+            .collect()
+    }
+
+    /// One line per bound action, listing its keys.
+    pub fn help(&self) -> String {
+        let mut help = String::new();
+        for &action in Action::ALL.iter() {
+            let keys: Vec<String> = self.keys_for(action).iter().map(Key::name).collect();
+            if !keys.is_empty() {
+                help += &format!("{:<20}{}\n", action.description(), keys.join(", "));
+            }
+        }
+        help
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct KeysFile {
+    #[serde(default = "all_profiles")]
+    profiles: Vec<Profile>,
+    /// Key names mapped to actions, on top of the profiles.
+    #[serde(default)]
+    bindings: BTreeMap<String, Action>,
+}
+
+fn all_profiles() -> Vec<Profile> {
+    vec![Profile::Arrows, Profile::Numpad, Profile::Vi]
+}
+
+/// Reads the key bindings. Call this at startup, so that a broken keys file
+/// is reported before the game starts.
+pub fn load() -> Result<(), Box<dyn Error>> {
+    if BINDINGS.get().is_none() {
+        let bindings = read()?;
+        let _ = BINDINGS.set(bindings);
+    }
+    Ok(())
+}
+
+/// The bindings read by `load`, reading them now if it was never called.
+pub fn bindings() -> &'static Bindings {
+    BINDINGS.get_or_init(|| read().unwrap_or_else(|e| panic!("{}", e)))
+}
+
+/// The bindings from the keys file, or every profile if there is none.
+fn read() -> Result<Bindings, Box<dyn Error>> {
+    match fs::read_to_string(KEYS_FILE) {
+        Ok(text) => parse_bindings(KEYS_FILE, &text),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => {
+            Ok(Bindings::from_profiles(&all_profiles()))
+        }
+        Err(e) => Err(format!("Could not read {}: {}", KEYS_FILE, e).into()),
+    }
+}
+
+/// Parses the key bindings in `text`; `path` is only used in errors.
+pub fn parse_bindings(path: &str, text: &str) -> Result<Bindings, Box<dyn Error>> {
+    let file: KeysFile = serde_json::from_str(text).map_err(|e| format!("{}: {}", path, e))?;
+    let mut bindings = Bindings::from_profiles(&file.profiles);
+    for (name, action) in file.bindings {
+        let key = Key::parse(&name).map_err(|e| format!("{}: {}", path, e))?;
+        bindings.bind(key, action);
+    }
+    Ok(bindings)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn key_profiles_and_the_keys_file_bind_actions() {
+        let vi = Bindings::from_profiles(&[Profile::Vi]);
+        assert_eq!(vi.action_for(KeyPress::text('y')), Some(Action::MoveUpLeft));
+        assert_eq!(vi.action_for(KeyPress::text('g')), Some(Action::PickUp));
+        assert_eq!(vi.action_for(KeyPress::code(KeyCode::Up)), None);
+        let numpad = Bindings::from_profiles(&[Profile::Numpad]);
+        let wait = numpad.action_for(KeyPress::code(KeyCode::NumPad5));
+        assert_eq!(wait, Some(Action::Wait));
+
+        let text = r#"{"profiles": ["vi"], "bindings": {"k": "Wait", "w": "MoveUp"}}"#;
+        let bindings = parse_bindings("keys.json", text).unwrap();
+        assert_eq!(bindings.action_for(KeyPress::text('k')), Some(Action::Wait));
+        assert_eq!(
+            bindings.action_for(KeyPress::text('w')),
+            Some(Action::MoveUp)
+        );
+        let help = bindings.help();
+        assert!(help.contains("Move up             w\n"));
+        assert!(help.contains("Wait a turn         ., k\n"));
+
+        let text = r#"{"bindings": {"Hyper+k": "Wait"}}"#;
+        let error = parse_bindings("keys.json", text).unwrap_err();
+        assert_eq!(error.to_string(), "keys.json: unknown key \"Hyper+k\"");
+    }
+}
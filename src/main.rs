@@ -1,29 +1,52 @@
 mod ai;
+mod command;
 mod components;
+mod dungeon;
+mod frontend;
 mod game;
+mod history;
 mod inventory;
+mod keys;
+mod mapgen;
 mod messages;
 mod object;
+mod pathfinding;
+mod render;
+mod replay;
+mod rng;
 mod roomgen;
+mod save;
+mod scheduler;
 mod statusbar;
+mod targeting;
+mod templates;
+#[cfg(unix)]
+mod terminal;
+#[cfg(test)]
 mod test;
 mod tile;
-use core::num;
 use std::cmp;
+use std::collections::HashMap;
 
-use components::Item;
+use command::Command;
+use components::{Stairs, Stat};
+use frontend::{Frontend, Input, KeyPress, TcodFrontend};
 use game::Game;
-use inventory::inventory_menu;
+use inventory::{inventory_menu, menu, msgbox, use_item_command};
+use keys::Action;
 use object::Object;
 use rand::Rng;
+use render::{Canvas, Layer, Screen};
+use replay::{Controller, Keyboard, Playback};
+use rng::GameRng;
 use roomgen::Rect;
-use tcod::colors;
 use tcod::colors::*;
-use tcod::console::*;
-use tcod::input::{self, Event, Key, Mouse};
+use tcod::input::Mouse;
+use tcod::TextAlignment;
+use templates::{choose_item, choose_monster, from_dungeon_level, Transition};
 
 use tcod::map::{FovAlgorithm, Map as FovMap};
-use tile::Tile;
+use tile::{Tile, TileKind};
 
 use crate::components::Fighter;
 use crate::messages::Messages;
@@ -32,14 +55,28 @@ use crate::statusbar::render_bar;
 const MSG_X: i32 = BAR_WIDTH + 2;
 const MSG_WIDTH: i32 = SCREEN_WIDTH - BAR_WIDTH - 2;
 const MSG_HEIGHT: usize = PANEL_HEIGHT as usize - 1;
+/// How many messages the log keeps before dropping the oldest.
+const MAX_MESSAGES: usize = 1000;
+/// How far messages from earlier turns fade towards black in the panel.
+const OLD_MESSAGE_FADE: f32 = 0.5;
 const SCREEN_WIDTH: i32 = 80;
 const BAR_WIDTH: i32 = 20;
 const PANEL_HEIGHT: i32 = 7;
 const PANEL_Y: i32 = SCREEN_HEIGHT - PANEL_HEIGHT;
 type Map = Vec<Vec<tile::Tile>>;
-const MAX_ROOM_MONSTERS: i32 = 4;
-const MAX_ROOM_ITEMS: i32 = 3;
+/// Most monsters and items a room can have, by dungeon level.
+const MAX_ROOM_MONSTERS: &[Transition] = &[
+    Transition { level: 1, value: 2 },
+    Transition { level: 4, value: 3 },
+    Transition { level: 6, value: 5 },
+];
+const MAX_ROOM_ITEMS: &[Transition] = &[
+    Transition { level: 1, value: 1 },
+    Transition { level: 4, value: 2 },
+    Transition { level: 7, value: 3 },
+];
 const INVENTORY_WIDTH: i32 = 50;
+const HELP_WIDTH: i32 = 60;
 const SCREEN_HEIGHT: i32 = 50;
 const MAP_WIDTH: i32 = 80;
 const MAP_HEIGHT: i32 = 43;
@@ -59,6 +96,44 @@ const COLOR_LIGHT_GROUND: Color = Color {
     g: 180,
     b: 80,
 };
+const COLOR_LIGHT_WATER: Color = Color {
+    r: 60,
+    g: 110,
+    b: 210,
+};
+const COLOR_DARK_WATER: Color = Color {
+    r: 30,
+    g: 50,
+    b: 130,
+};
+const COLOR_LIGHT_DEEP_WATER: Color = Color {
+    r: 20,
+    g: 50,
+    b: 160,
+};
+const COLOR_DARK_DEEP_WATER: Color = Color {
+    r: 10,
+    g: 25,
+    b: 90,
+};
+const COLOR_LIGHT_LAVA: Color = Color {
+    r: 230,
+    g: 80,
+    b: 20,
+};
+const COLOR_DARK_LAVA: Color = Color {
+    r: 110,
+    g: 40,
+    b: 10,
+};
+const COLOR_DOOR: Color = DARK_SEPIA;
+const COLOR_TARGET_RANGE: Color = Color {
+    r: 225,
+    g: 210,
+    b: 130,
+};
+const COLOR_LINE_OF_FIRE: Color = LIGHT_ORANGE;
+const COLOR_BLOCKED_LINE: Color = DARK_RED;
 const FPS: i32 = 25;
 const ROOM_MAX_SIZE: i32 = 10;
 const ROOM_MIN_SIZE: i32 = 6;
@@ -66,25 +141,73 @@ const MAX_ROOMS: i32 = 30;
 const FOV_ALGORITHM: FovAlgorithm = FovAlgorithm::Basic;
 const FOV_LIGHT_WALLS: bool = true;
 const TORCH_RADIUS: i32 = 10;
+const HEAL_AMOUNT: i32 = 4;
+const LAVA_DAMAGE: i32 = 10;
+const LIGHTNING_DAMAGE: i32 = 40;
+const LIGHTNING_RANGE: i32 = 5;
+const CONFUSE_RANGE: i32 = 8;
+const CONFUSE_NUM_TURNS: i32 = 10;
+const FIREBALL_RADIUS: i32 = 3;
+const FIREBALL_DAMAGE: i32 = 12;
+const START_MENU_WIDTH: i32 = 24;
+const DUNGEON_DEPTH: u32 = 10;
+const MAX_PATH_LENGTH: usize = 25;
+/// Energy an actor needs to act; moving or attacking spends exactly this.
+const ACTION_COST: i32 = 100;
+const PICK_UP_COST: i32 = 50;
+const DROP_COST: i32 = 50;
+const USE_ITEM_COST: i32 = 100;
+const NORMAL_SPEED: i32 = 100;
+const LEVEL_UP_BASE: i32 = 200;
+const LEVEL_UP_FACTOR: i32 = 150;
+const LEVEL_SCREEN_WIDTH: i32 = 40;
 
-struct Tcod {
-    root: Root,
-    con: Offscreen,
-    panel: Offscreen,
+pub struct Tcod<F> {
+    frontend: F,
     fov: FovMap,
-    key: Key,
+    key: KeyPress,
     mouse: Mouse,
 }
 
 #[derive(Clone, Copy, Debug, PartialEq)]
 enum PlayerAction {
-    TookTurn,
+    TookTurn(i32),
     DidntTakeTurn,
     Exit,
+    Won,
 }
 
-pub fn is_blocked(x: i32, y: i32, map: &Map, objects: &Vec<Object>) -> bool {
-    if map[x as usize][y as usize].blocked {
+/// What a key press asks for.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum PlayerInput {
+    Command(Command),
+    Exit,
+    /// Nothing for the game to do, as after looking around or cancelling a
+    /// menu.
+    Nothing,
+}
+
+/// The top-level screens the game moves between.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum GameState {
+    MainMenu,
+    NewGame,
+    Continue,
+    Playing,
+    Dead,
+    Victory,
+    Quit,
+}
+
+/// Whether (x, y) can't be stepped on by anything that can't swim: walls,
+/// closed doors, deep water and whatever blocks stand in the way.
+pub fn is_blocked(x: i32, y: i32, map: &Map, objects: &[Object]) -> bool {
+    is_blocked_for(false, x, y, map, objects)
+}
+
+/// Like `is_blocked`, but lets swimmers through deep water.
+pub fn is_blocked_for(swims: bool, x: i32, y: i32, map: &Map, objects: &[Object]) -> bool {
+    if !map[x as usize][y as usize].walkable_by(swims) {
         return true;
     };
     objects
@@ -105,7 +228,13 @@ fn mut_two<T>(first_index: usize, second_index: usize, items: &mut [T]) -> (&mut
     }
 }
 
-fn move_towards(id: usize, target_x: i32, target_y: i32, game: &Game, objects: &mut Vec<Object>) {
+fn move_towards(
+    id: usize,
+    target_x: i32,
+    target_y: i32,
+    game: &mut Game,
+    objects: &mut Vec<Object>,
+) {
     let dx = target_x - objects[id].x;
     let dy = target_y - objects[id].y;
     let distance = ((dx.pow(2) + dy.pow(2)) as f32).sqrt();
@@ -114,7 +243,8 @@ fn move_towards(id: usize, target_x: i32, target_y: i32, game: &Game, objects: &
     move_by(id, dx, dy, game, objects)
 }
 
-pub fn pick_item_up(object_id: usize, game: &mut Game, objects: &mut Vec<Object>) {
+/// Moves an item into the inventory, returning whether there was room for it.
+pub fn pick_item_up(object_id: usize, game: &mut Game, objects: &mut Vec<Object>) -> bool {
     if game.inventory.len() >= 26 {
         game.messages.add(
             format!(
@@ -123,12 +253,27 @@ pub fn pick_item_up(object_id: usize, game: &mut Game, objects: &mut Vec<Object>
             ),
             RED,
         );
+        false
     } else {
         let item = objects.swap_remove(object_id);
         game.messages
             .add(format!("You picked up a {}!", item.name), GREEN);
         game.inventory.push(item);
+        true
+    }
+}
+
+/// Puts an inventory item down where the player stands, taking it off first
+/// if it is worn.
+pub fn drop_item(inventory_id: usize, game: &mut Game, objects: &mut Vec<Object>) {
+    let mut item = game.inventory.remove(inventory_id);
+    if item.equipment.is_some() {
+        item.unequip(&mut objects[0], &mut game.messages);
     }
+    item.set_pos(objects[0].x, objects[0].y);
+    game.messages
+        .add(format!("You dropped a {}.", item.name), YELLOW);
+    objects.push(item);
 }
 
 pub fn player_move_or_attack(
@@ -154,10 +299,8 @@ pub fn player_move_or_attack(
     }
 }
 
-fn get_names_under_mouse(mouse: Mouse, objects: &[Object], fov_map: &FovMap) -> String {
-    let (x, y) = (mouse.cx as i32, mouse.cy as i32);
-
-    // create a list with the names of all objects at the mouse's coordinates and in FOV
+fn names_at(x: i32, y: i32, objects: &[Object], fov_map: &FovMap) -> String {
+    // create a list with the names of all objects at these coordinates and in FOV
     let names = objects
         .iter()
         .filter(|obj| obj.pos() == (x, y) && fov_map.is_in_fov(obj.x, obj.y))
@@ -167,133 +310,222 @@ fn get_names_under_mouse(mouse: Mouse, objects: &[Object], fov_map: &FovMap) ->
     names.join(", ") // join the names, separated by commas
 }
 
-pub fn move_by(id: usize, dx: i32, dy: i32, game: &game::Game, objects: &mut Vec<Object>) {
+pub fn move_by(id: usize, dx: i32, dy: i32, game: &mut Game, objects: &mut Vec<Object>) {
     let (x, y) = objects[id].pos();
-    if !is_blocked(x + dx, y + dy, &game.map, objects) {
+    // bumping into a closed door opens it, which takes the move
+    if open_door(x + dx, y + dy, game) {
+        return;
+    }
+    let tile = game.map[(x + dx) as usize][(y + dy) as usize];
+    if !is_blocked_for(objects[id].swims, x + dx, y + dy, &game.map, objects) {
         objects[id].set_pos(x + dx, y + dy);
+        // the move itself costs ACTION_COST; slow ground takes the rest
+        objects[id].energy -= tile.kind.move_cost() - ACTION_COST;
+        enter_tile(id, game, objects);
     }
 }
 
-fn place_objects(room: &Rect, objects: &mut Vec<Object>, game: &game::Game) {
-    let num_monsters = rand::thread_rng().gen_range(0, MAX_ROOM_MONSTERS + 1);
-    for _ in 0..num_monsters {
-        let x = rand::thread_rng().gen_range(room.x1 + 1, room.x2);
-        let y = rand::thread_rng().gen_range(room.y1 + 1, room.y2);
-        if is_blocked(x, y, &game.map, objects) {
-            let mut monster = if rand::random::<f32>() < 0.8 {
-                let mut orc = Object::new(
-                    x,
-                    y,
-                    'o',
-                    colors::DESATURATED_GREEN,
-                    "Orc".to_string(),
-                    true,
-                    true,
-                );
-                orc.ai = Some(components::Ai::Basic);
-                orc.fighter = Some(Fighter {
-                    max_hp: 10,
-                    hp: 10,
-                    defense: 0,
-                    power: 3,
-                    on_death: components::DeathCallback::Monster,
-                });
-                orc
-            } else {
-                let mut troll = Object::new(
-                    x,
-                    y,
-                    'T',
-                    colors::DARKER_GREEN,
-                    "Troll".to_string(),
-                    true,
-                    true,
-                ); //troll
-                troll.ai = Some(components::Ai::Basic);
-                troll.fighter = Some(Fighter {
-                    max_hp: 16,
-                    hp: 16,
-                    defense: 1,
-                    power: 4,
-                    on_death: components::DeathCallback::Monster,
-                });
-                troll
-            };
-
-            objects.push(monster);
-        }
+/// Whatever the tile under the object does to those who step on it.
+fn enter_tile(id: usize, game: &mut Game, objects: &mut [Object]) {
+    let (x, y) = objects[id].pos();
+    if game.map[x as usize][y as usize].kind == TileKind::Lava && objects[id].fighter.is_some() {
+        game.messages.add(
+            format!(
+                "{} is burned by the lava for {} hit points.",
+                objects[id].name, LAVA_DAMAGE
+            ),
+            ORANGE,
+        );
+        objects[id].take_damage(LAVA_DAMAGE, game);
     }
+}
 
-    let num_items = rand::thread_rng().gen_range(0, MAX_ROOM_ITEMS + 1);
-    for _ in 0..num_items {
-        let x = rand::thread_rng().gen_range(room.x1 + 1, room.x2);
-        let y = rand::thread_rng().gen_range(room.y1 + 1, room.y2);
+/// Opens the door at (x, y) if it is closed, returning whether it was.
+pub fn open_door(x: i32, y: i32, game: &mut Game) -> bool {
+    let tile = &mut game.map[x as usize][y as usize];
+    if !tile.is_closed_door() {
+        return false;
+    }
+    tile.set_open(true);
+    game.changed_tiles.push((x, y));
+    true
+}
 
-        if is_blocked(x, y, &game.map, objects) {
-            let mut object = Object::new(
-                x,
-                y,
-                '!',
-                VIOLET,
-                "Potion of Healing".to_string(),
+/// Closes the open door at (x, y), unless something is in the doorway.
+/// Returns whether it did.
+pub fn close_door(x: i32, y: i32, game: &mut Game, objects: &[Object]) -> bool {
+    if !game.map[x as usize][y as usize].is_open_door() {
+        return false;
+    }
+    if objects.iter().any(|object| object.pos() == (x, y)) {
+        game.messages.add("Something is in the way.", WHITE);
+        return false;
+    }
+    game.map[x as usize][y as usize].set_open(false);
+    game.changed_tiles.push((x, y));
+    game.messages.add("You close the door.", WHITE);
+    true
+}
+
+/// Finds the open door next to the player, asking which one if there are
+/// several, and returns the direction to it.
+fn door_next_to_player<F: Frontend>(
+    tcod: &mut Tcod<F>,
+    game: &mut Game,
+    objects: &[Object],
+) -> Option<(i32, i32)> {
+    let (x, y) = objects[0].pos();
+    let open_doors: Vec<(i32, i32)> = [(0, -1), (0, 1), (-1, 0), (1, 0)]
+        .iter()
+        .copied()
+        .filter(|&(dx, dy)| game.map[(x + dx) as usize][(y + dy) as usize].is_open_door())
+        .collect();
+    match open_doors[..] {
+        [] => {
+            game.messages
+                .add("There is no open door next to you.", WHITE);
+            None
+        }
+        [door] => Some(door),
+        _ => {
+            game.messages.add(
+                "Close which door? Press the direction key towards it.",
+                LIGHT_CYAN,
+            );
+            tcod.frontend.screen().con.clear(BLACK);
+            render_all(
+                tcod.frontend.screen(),
+                &mut tcod.fov,
+                tcod.mouse,
+                game,
+                objects,
                 false,
-                true,
             );
-            object.item = Some(Item::Heal);
-            objects.push(object);
+            tcod.frontend.flush();
+            let key = tcod.frontend.wait_for_keypress();
+            keys::bindings()
+                .action_for(key)
+                .and_then(|action| action.direction())
+        }
+    }
+}
+
+/// Copies the tiles changed since the last update into the FOV map, and
+/// returns whether there were any.
+fn update_fov(fov: &mut FovMap, game: &mut Game) -> bool {
+    let changed = !game.changed_tiles.is_empty();
+    for (x, y) in game.changed_tiles.drain(..) {
+        let tile = &game.map[x as usize][y as usize];
+        fov.set(x, y, !tile.block_sight, !tile.blocked);
+    }
+    changed
+}
+
+/// Spawns up to a room's worth of monsters and items for `level`, each on a
+/// tile picked by `random_spot` unless something is already there.
+fn place_objects<S>(
+    mut random_spot: S,
+    map: &Map,
+    objects: &mut Vec<Object>,
+    level: u32,
+    rng: &mut GameRng,
+) where
+    S: FnMut(&mut GameRng) -> (i32, i32),
+{
+    let max_monsters = from_dungeon_level(MAX_ROOM_MONSTERS, level);
+    let num_monsters = rng.gen_range(0, max_monsters + 1);
+    for _ in 0..num_monsters {
+        let (x, y) = random_spot(rng);
+        if !is_blocked(x, y, map, objects) && map[x as usize][y as usize].kind.is_safe() {
+            objects.push(choose_monster(rng, level).spawn(x, y));
+        }
+    }
+
+    let max_items = from_dungeon_level(MAX_ROOM_ITEMS, level);
+    let num_items = rng.gen_range(0, max_items + 1);
+    for _ in 0..num_items {
+        let (x, y) = random_spot(rng);
+        if !is_blocked(x, y, map, objects) && map[x as usize][y as usize].kind.is_safe() {
+            objects.push(choose_item(rng, level).spawn(x, y));
         }
     }
 }
 
 fn make_empty_map() -> Map {
-    let mut map = vec![vec![Tile::empty(); MAP_HEIGHT as usize]; MAP_WIDTH as usize];
-    map
+    vec![vec![Tile::empty(); MAP_HEIGHT as usize]; MAP_WIDTH as usize]
 }
 
-fn make_map(objects: &mut Vec<Object>) -> Map {
-    let mut map = vec![vec![Tile::wall(); MAP_HEIGHT as usize]; MAP_WIDTH as usize];
-    let game = Game {
-        map: map.clone(),
-        messages: Messages::new(),
-        inventory: vec![],
-    };
-    //let room1 = Rect::new(20,15,10,15);
-    //let room2 = Rect::new(50,15,10,15);
-    //create_room(room1, &mut map);
-    //create_room(room2, &mut map);
-    //create_h_tunnel(25, 55, 23, &mut map);
-    let mut rooms = vec![];
-
-    for _ in 0..MAX_ROOMS {
-        let w = rand::thread_rng().gen_range(ROOM_MIN_SIZE, ROOM_MAX_SIZE + 1);
-        let h = rand::thread_rng().gen_range(ROOM_MIN_SIZE, ROOM_MAX_SIZE + 1);
-        let x = rand::thread_rng().gen_range(0, MAP_WIDTH - w);
-        let y = rand::thread_rng().gen_range(0, MAP_HEIGHT - h);
-        let new_room = Rect::new(x, y, w, h);
-        let failed = rooms
-            .iter()
-            .any(|other_room| new_room.intersects_with(other_room));
-        if !failed {
-            let (new_x, new_y) = new_room.center();
-            create_room(new_room.clone(), &mut map);
-            place_objects(&new_room, objects, &game);
-            if rooms.is_empty() {
-                objects[0].set_pos(new_x, new_y)
-            } else {
-                let (prev_x, prev_y) = rooms[rooms.len() - 1].center();
-                if rand::random() {
-                    create_h_tunnel(prev_x, new_x, prev_y, &mut map);
-                    create_v_tunnel(prev_y, new_y, new_x, &mut map);
-                } else {
-                    create_v_tunnel(prev_y, new_y, prev_x, &mut map);
-                    create_h_tunnel(prev_x, new_x, new_y, &mut map);
-                }
-            }
-            rooms.push(new_room.clone());
+fn make_map(objects: &mut Vec<Object>, level: u32, rng: &mut GameRng) -> Map {
+    loop {
+        let generator = mapgen::generator_for(level, rng);
+        if let Some(map) = build_level(&*generator, objects, level, rng) {
+            return map;
         }
     }
+}
+
+/// Generates a level with `generator` and puts the stairs on it. Returns None,
+/// with only the player left in `objects`, if the map can't be fully joined
+/// up.
+fn build_level(
+    generator: &dyn mapgen::MapGenerator,
+    objects: &mut Vec<Object>,
+    level: u32,
+    rng: &mut GameRng,
+) -> Option<Map> {
+    let mut layout = generator.generate(objects, level, rng);
+    let (first_x, first_y) = layout.start;
+    objects[0].set_pos(first_x, first_y);
 
-    map
+    // stairs back up sit where the player arrives, stairs down at the exit
+    if level > 1 {
+        let mut stairs = Object::new(
+            first_x,
+            first_y,
+            '<',
+            WHITE,
+            "stairs up".to_string(),
+            false,
+            false,
+        );
+        stairs.always_visible = true;
+        stairs.stairs = Some(Stairs::Up);
+        objects.push(stairs);
+    }
+    let (last_x, last_y) = layout.exit;
+    let mut stairs = Object::new(
+        last_x,
+        last_y,
+        '>',
+        WHITE,
+        "stairs down".to_string(),
+        false,
+        false,
+    );
+    stairs.always_visible = true;
+    stairs.stairs = Some(Stairs::Down);
+    objects.push(stairs);
+
+    // extra tunnels reach anything the generator cut off; a map that
+    // cannot be fixed that way is thrown away
+    if mapgen::connect_unreachable(&mut layout.map, objects, rng) {
+        return Some(layout.map);
+    }
+    objects.truncate(1);
+    None
+}
+
+fn initialise_fov(fov: &mut FovMap, map: &Map) {
+    for y in 0..MAP_HEIGHT {
+        for x in 0..MAP_WIDTH {
+            fov.set(
+                x,
+                y,
+                !map[x as usize][y as usize].block_sight,
+                !map[x as usize][y as usize].blocked,
+            );
+        }
+    }
 }
 
 fn create_room(room: Rect, map: &mut Map) {
@@ -316,10 +548,17 @@ fn create_v_tunnel(y1: i32, y2: i32, x: i32, map: &mut Map) {
     }
 }
 
-fn render_all(tcod: &mut Tcod, game: &mut Game, objects: &[Object], fov_recompute: bool) {
+fn render_all<R: Canvas, L: Layer<R>>(
+    screen: &mut Screen<R, L>,
+    fov: &mut FovMap,
+    mouse: Mouse,
+    game: &mut Game,
+    objects: &[Object],
+    fov_recompute: bool,
+) {
     if fov_recompute {
         let player = &objects[0];
-        tcod.fov.compute_fov(
+        fov.compute_fov(
             player.x,
             player.y,
             TORCH_RADIUS,
@@ -328,55 +567,52 @@ fn render_all(tcod: &mut Tcod, game: &mut Game, objects: &[Object], fov_recomput
         );
     }
 
-    let mut to_draw: Vec<_> = objects
-        .iter()
-        .filter(|o| tcod.fov.is_in_fov(o.x, o.y))
-        .collect();
-    to_draw.sort_by(|o1, o2| o1.blocks_motion.cmp(&o2.blocks_motion));
-    for object in to_draw {
-        if tcod.fov.is_in_fov(object.x, object.y) {
-            object.draw(&mut tcod.con);
-        }
-    }
-
     for y in 0..MAP_HEIGHT {
         for x in 0..MAP_WIDTH {
-            let visible = tcod.fov.is_in_fov(x, y);
-            let wall = game.map[x as usize][y as usize].block_sight;
-            let colour = match (visible, wall) {
-                (false, true) => COLOR_DARK_WALL,
-                (false, false) => COLOR_DARK_GROUND,
-                (true, true) => COLOR_LIGHT_WALL,
-                (true, false) => COLOR_LIGHT_GROUND,
-            };
-            let explored = &mut game.map[x as usize][y as usize].explored;
+            let visible = fov.is_in_fov(x, y);
+            let tile = &mut game.map[x as usize][y as usize];
+            let (light, dark) = tile.kind.colors();
+            let colour = if visible { light } else { dark };
             if visible {
-                *explored = true;
+                tile.explored = true;
             }
-            if *explored {
-                tcod.con
-                    .set_char_background(x, y, colour, BackgroundFlag::Set);
+            if tile.explored {
+                screen.con.set_background(x, y, colour);
+                if let Some((glyph, glyph_colour)) = tile.glyph() {
+                    screen.con.put_char(x, y, glyph, glyph_colour);
+                }
             }
         }
     }
 
-    blit(
-        &tcod.con,
+    // objects go on top of the map, so they show in open doorways
+    let mut to_draw: Vec<_> = objects
+        .iter()
+        .filter(|o| {
+            fov.is_in_fov(o.x, o.y)
+                || (o.always_visible && game.map[o.x as usize][o.y as usize].explored)
+        })
+        .collect();
+    to_draw.sort_by(|o1, o2| o1.blocks_motion.cmp(&o2.blocks_motion));
+    for object in to_draw {
+        object.draw(&mut screen.con);
+    }
+
+    screen.con.blit(
         (0, 0),
         (MAP_WIDTH, MAP_HEIGHT),
-        &mut tcod.root,
+        &mut screen.root,
         (0, 0),
         1.0,
         1.0,
     );
 
-    tcod.panel.set_default_background(BLACK);
-    tcod.panel.clear();
+    screen.panel.clear(BLACK);
 
     let hp = objects[0].fighter.map_or(0, |f| f.hp);
-    let max_hp = objects[0].fighter.map_or(0, |f| f.max_hp);
+    let max_hp = objects[0].max_hp();
     render_bar(
-        &mut tcod.panel,
+        &mut screen.panel,
         1,
         1,
         BAR_WIDTH,
@@ -387,231 +623,546 @@ fn render_all(tcod: &mut Tcod, game: &mut Game, objects: &[Object], fov_recomput
         DARKER_RED,
     );
 
-    tcod.panel.set_default_foreground(LIGHT_GREY);
-    tcod.panel.print_ex(
+    let xp = objects[0].fighter.map_or(0, |f| f.xp);
+    render_bar(
+        &mut screen.panel,
+        1,
+        2,
+        BAR_WIDTH,
+        "XP",
+        xp,
+        level_up_xp(objects[0].level),
+        LIGHT_PURPLE,
+        DARKER_PURPLE,
+    );
+
+    screen.panel.print(
+        1,
+        3,
+        TextAlignment::Left,
+        WHITE,
+        &format!("Dungeon level: {}", game.dungeon_level),
+    );
+    screen.panel.print(
+        1,
+        4,
+        TextAlignment::Left,
+        WHITE,
+        &format!("Level: {}", objects[0].level),
+    );
+
+    screen.panel.print(
         1,
         0,
-        BackgroundFlag::None,
         TextAlignment::Left,
-        get_names_under_mouse(tcod.mouse, objects, &tcod.fov),
+        LIGHT_GREY,
+        &names_at(mouse.cx as i32, mouse.cy as i32, objects, fov),
     );
 
     let mut y = MSG_HEIGHT as i32;
-    for &(ref msg, color) in game.messages.iter().rev() {
-        let msg_height = tcod.panel.get_height_rect(MSG_X, y, MSG_WIDTH, 0, msg);
+    // what happened on the last turn stands out from what came before
+    let current_turn = game.messages.turn();
+    for message in game.messages.iter().rev() {
+        let msg = &message.line();
+        let msg_height = screen.panel.get_height_rect(MSG_X, y, MSG_WIDTH, 0, msg);
         y -= msg_height;
         if y < 0 {
             break;
         }
-        tcod.panel.set_default_foreground(color);
-        tcod.panel.print_rect(MSG_X, y, MSG_WIDTH, 0, msg);
+        let colour = if message.turn + 1 >= current_turn {
+            message.colour
+        } else {
+            render::lerp(message.colour, BLACK, OLD_MESSAGE_FADE)
+        };
+        screen.panel.print_rect(MSG_X, y, MSG_WIDTH, 0, colour, msg);
     }
 
-    blit(
-        &tcod.panel,
+    screen.panel.blit(
         (0, 0),
         (SCREEN_WIDTH, PANEL_HEIGHT),
-        &mut tcod.root,
+        &mut screen.root,
         (0, PANEL_Y),
         1.0,
         1.0,
     )
 }
 
-fn handle_keys(
-    tcod: &mut Tcod,
+/// Turns the last key press into a command for the game. Anything that only
+/// concerns the interface, like menus and help, is dealt with right here.
+fn handle_keys<F: Frontend>(
+    tcod: &mut Tcod<F>,
     player_id: usize,
     objects: &mut Vec<Object>,
     game: &mut Game,
-) -> PlayerAction {
+) -> PlayerInput {
     let player_alive = objects[player_id].is_alive;
-    match (tcod.key, tcod.key.text(), player_alive) {
-        (
-            Key {
-                code: tcod::input::KeyCode::Enter,
-                alt: true,
-                ..
-            },
-            _,
-            _,
-        ) => {
-            let fullscreen = tcod.root.is_fullscreen();
-            tcod.root.set_fullscreen(!fullscreen);
-            return PlayerAction::DidntTakeTurn;
+    let action = match keys::bindings().action_for(tcod.key) {
+        Some(action) => action,
+        None => return PlayerInput::Nothing,
+    };
+    if let (Some((dx, dy)), true) = (action.direction(), player_alive) {
+        return PlayerInput::Command(Command::Move(dx, dy));
+    }
+    match (action, player_alive) {
+        (Action::ToggleFullscreen, _) => {
+            tcod.frontend.toggle_fullscreen();
+            return PlayerInput::Nothing;
         }
-        (
-            Key {
-                code: tcod::input::KeyCode::Escape,
-                ..
-            },
-            _,
-            _,
-        ) => return PlayerAction::Exit,
-        (
-            Key {
-                code: tcod::input::KeyCode::Up,
-                ..
-            },
-            _,
-            true,
-        ) => {
-            player_move_or_attack(player_id, 0, -1, game, objects);
-            return PlayerAction::TookTurn;
+        (Action::Exit, _) => return PlayerInput::Exit,
+        (Action::Wait, true) => return PlayerInput::Command(Command::Wait),
+        (Action::PickUp, true) => return PlayerInput::Command(Command::PickUp),
+        (Action::Inventory, true) => {
+            // show the inventory: if an item is selected, use it
+            let inventory_index = inventory_menu(
+                &game.inventory,
+                "Press the key next to an item to use it, or any other to cancel.\n",
+                tcod,
+            );
+            if let Some(inventory_index) = inventory_index {
+                if let Some(command) = use_item_command(inventory_index, tcod, game, objects) {
+                    return PlayerInput::Command(command);
+                }
+            }
+            return PlayerInput::Nothing;
         }
-        (
-            Key {
-                code: tcod::input::KeyCode::Down,
-                ..
-            },
-            _,
-            true,
-        ) => {
-            player_move_or_attack(player_id, 0, 1, game, objects);
-            return PlayerAction::TookTurn;
+        (Action::Drop, true) => {
+            let inventory_index = inventory_menu(
+                &game.inventory,
+                "Press the key next to an item to drop it, or any other to cancel.\n",
+                tcod,
+            );
+            if let Some(inventory_index) = inventory_index {
+                return PlayerInput::Command(Command::Drop(inventory_index));
+            }
+            return PlayerInput::Nothing;
         }
-        (
-            Key {
-                code: tcod::input::KeyCode::Left,
-                ..
-            },
-            _,
-            true,
-        ) => {
-            player_move_or_attack(player_id, -1, 0, game, objects);
-            return PlayerAction::TookTurn;
+        (Action::Descend, true) => return PlayerInput::Command(Command::Descend),
+        (Action::Ascend, true) => return PlayerInput::Command(Command::Ascend),
+        (Action::CloseDoor, true) => {
+            if let Some((dx, dy)) = door_next_to_player(tcod, game, objects) {
+                return PlayerInput::Command(Command::CloseDoor(dx, dy));
+            }
+            return PlayerInput::Nothing;
         }
-        (
-            Key {
-                code: tcod::input::KeyCode::Right,
-                ..
-            },
-            _,
-            true,
-        ) => {
-            player_move_or_attack(player_id, 1, 0, game, objects);
-            return PlayerAction::TookTurn;
+        (Action::MessageLog, _) => {
+            // the full message log
+            history::show_history(tcod, &game.messages);
+            return PlayerInput::Nothing;
         }
-        (
-            Key {
-                code: tcod::input::KeyCode::Text,
-                ..
-            },
-            "g",
-            true,
-        ) => {
-            let item_id = objects
-                .iter()
-                .position(|object| object.pos() == objects[0].pos() && object.item.is_some());
-            if let Some(item_id) = item_id {
-                pick_item_up(item_id, game, objects);
-            }
-            return PlayerAction::DidntTakeTurn;
+        (Action::Help, _) => {
+            let help = format!("\n{}", keys::bindings().help());
+            msgbox(&help, HELP_WIDTH, tcod);
+            return PlayerInput::Nothing;
         }
-        (
-            Key {
-                code: tcod::input::KeyCode::Text,
-                ..
-            },
-            "i",
-            true,
-        ) => {
-            // show the inventory
-            inventory_menu(
-                &game.inventory,
-                "Press the key next to an item to use it, or any other to cancel.\n",
-                &mut tcod.root,
+        (Action::Look, true) => {
+            // look around with the targeting cursor
+            game.messages.add(
+                "Look around with the movement keys, Tab or the mouse; Escape to stop.",
+                LIGHT_CYAN,
             );
-            return PlayerAction::TookTurn;
+            if let Some((x, y)) = targeting::look(tcod, game, objects) {
+                let names = names_at(x, y, objects, &tcod.fov);
+                if names.is_empty() {
+                    game.messages.add("You see nothing of interest.", WHITE);
+                } else {
+                    game.messages.add(format!("You see: {}.", names), WHITE);
+                }
+            }
+            return PlayerInput::Nothing;
         }
 
         _ => {
-            return PlayerAction::DidntTakeTurn;
+            return PlayerInput::Nothing;
         }
     }
 }
 
-fn main() {
-    //map[30][22] = Tile::wall();
-    //map[33][24] = Tile::wall();
+/// The replay file given with `--replay <file>`, if any.
+fn replay_from_args() -> Option<String> {
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        if arg == "--replay" {
+            return Some(args.next().expect("--replay needs a file"));
+        }
+    }
+    None
+}
 
-    let mut root = Root::initializer()
-        .font("./resources/arial10x10.png", FontLayout::Tcod)
-        .font_type(FontType::Greyscale)
-        .size(SCREEN_WIDTH, SCREEN_HEIGHT)
-        .title("Tutorial")
-        .init();
+/// Reads the dungeon seed from `--seed <u64>`, or picks one from the clock.
+fn seed_from_args() -> u64 {
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        if arg == "--seed" {
+            let value = args.next().expect("--seed needs a value");
+            return value
+                .parse()
+                .unwrap_or_else(|_| panic!("Invalid seed: {}", value));
+        }
+    }
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map_or(0, |time| time.as_nanos() as u64)
+}
 
+fn new_game(seed: u64) -> (Game, Vec<Object>) {
     let mut player = object::Object::new(25, 23, '@', WHITE, "me".to_string(), true, true);
     player.fighter = Some(Fighter {
-        max_hp: 30,
+        base_max_hp: 30,
         hp: 30,
-        defense: 2,
-        power: 5,
+        base_defense: 2,
+        base_power: 5,
+        speed: NORMAL_SPEED,
+        xp: 0,
         on_death: components::DeathCallback::Player,
     });
     let mut objects = vec![player];
-    let map = make_map(&mut objects);
+    let mut rng = GameRng::new(seed);
+    let map = make_map(&mut objects, 1, &mut rng);
     let mut game = Game {
         map: map,
         messages: Messages::new(),
         inventory: vec![],
+        rng: rng,
+        dungeon_level: 1,
+        levels: HashMap::new(),
+        changed_tiles: vec![],
     };
+    game.messages
+        .add("Welcome stranger! Prepare to perish in the dungeon.", RED);
+    (game, objects)
+}
 
-    let con = Offscreen::new(MAP_WIDTH, MAP_HEIGHT);
-    let panel = Offscreen::new(SCREEN_WIDTH, PANEL_HEIGHT);
+fn main() {
+    if let Err(e) = templates::load().and_then(|_| keys::load()) {
+        eprintln!("{}", e);
+        std::process::exit(1);
+    }
+
+    // `--terminal` plays in the terminal instead of opening a window
+    if std::env::args().any(|arg| arg == "--terminal") {
+        run_in_terminal();
+    } else {
+        run(TcodFrontend::new());
+    }
+}
+
+#[cfg(unix)]
+fn run_in_terminal() {
+    match terminal::TerminalFrontend::new() {
+        Ok(frontend) => run(frontend),
+        Err(e) => eprintln!("Could not play in the terminal: {}", e),
+    }
+}
+
+#[cfg(not(unix))]
+fn run_in_terminal() {
+    eprintln!("Playing in the terminal is only supported on Unix.");
+}
+
+fn run<F: Frontend>(frontend: F) {
     let fov = FovMap::new(MAP_WIDTH, MAP_HEIGHT);
     let mut tcod = Tcod {
-        root: root,
-        con: con,
-        panel: panel,
+        frontend: frontend,
         fov: fov,
         mouse: Default::default(),
         key: Default::default(),
     };
-    tcod::system::set_fps(FPS);
 
-    let _player_x = SCREEN_WIDTH / 2;
-    let _player_y = SCREEN_HEIGHT / 2;
+    // `--replay <file>` plays a recorded game back instead
+    if let Some(path) = replay_from_args() {
+        replay_game(&mut tcod, &path);
+        return;
+    }
 
-    for y in 0..MAP_HEIGHT {
-        for x in 0..MAP_WIDTH {
-            tcod.fov.set(
-                x,
-                y,
-                !game.map[x as usize][y as usize].block_sight,
-                !game.map[x as usize][y as usize].blocked,
-            );
+    let mut state = GameState::MainMenu;
+    let mut session: Option<(Game, Vec<Object>)> = None;
+    let mut keyboard = Keyboard::default();
+    while state != GameState::Quit && !tcod.frontend.window_closed() {
+        state = match state {
+            GameState::MainMenu => main_menu(&mut tcod),
+            GameState::NewGame => {
+                let seed = seed_from_args();
+                println!("Seed: {}", seed);
+                session = Some(new_game(seed));
+                keyboard.start_recording(replay::REPLAY_FILE, seed);
+                GameState::Playing
+            }
+            GameState::Continue => match save::load_game() {
+                Ok(loaded) => {
+                    keyboard.resume_recording(replay::REPLAY_FILE, loaded.0.rng.seed());
+                    session = Some(loaded);
+                    GameState::Playing
+                }
+                Err(e) => {
+                    msgbox(
+                        &format!("\nCould not load the saved game: {}\n", e),
+                        INVENTORY_WIDTH,
+                        &mut tcod,
+                    );
+                    GameState::MainMenu
+                }
+            },
+            GameState::Playing => match session {
+                Some((ref mut game, ref mut objects)) => {
+                    let state = play_game(&mut tcod, game, objects, &mut keyboard);
+                    keyboard.record_state(game, objects);
+                    if state == GameState::MainMenu {
+                        // save and quit to the main menu
+                        save_game(&mut tcod, game, objects);
+                    }
+                    state
+                }
+                None => GameState::MainMenu,
+            },
+            GameState::Dead => {
+                session = None;
+                end_run(
+                    &mut tcod,
+                    "\nYou died! Press any key to return to the main menu.\n",
+                )
+            }
+            GameState::Victory => {
+                session = None;
+                end_run(
+                    &mut tcod,
+                    "\nYou escaped the dungeon alive! Press any key to return to the main menu.\n",
+                )
+            }
+            GameState::Quit => GameState::Quit,
+        };
+    }
+
+    // closing the window mid-run still keeps the game for later
+    if let Some((ref game, ref objects)) = session {
+        if objects[0].is_alive {
+            save_game(&mut tcod, game, objects);
         }
     }
+}
+
+/// Plays back the replay at `path`, then tells whether the game went the way
+/// it was recorded.
+fn replay_game<F: Frontend>(tcod: &mut Tcod<F>, path: &str) {
+    let replay = match replay::load_replay(path) {
+        Ok(replay) => replay,
+        Err(e) => {
+            eprintln!("Could not load the replay: {}", e);
+            return;
+        }
+    };
+    println!("Seed: {}", replay.seed);
+    let (mut game, mut objects) = new_game(replay.seed);
+    let mut playback = Playback::new(replay);
+    play_game(tcod, &mut game, &mut objects, &mut playback);
+    let summary = playback.finish(&game, &objects);
+    println!("{}", summary);
+    msgbox(&format!("\n{}\n", summary), INVENTORY_WIDTH, tcod);
+}
+
+fn main_menu<F: Frontend>(tcod: &mut Tcod<F>) -> GameState {
+    let screen = tcod.frontend.screen();
+    screen.root.clear(BLACK);
+    screen.root.print(
+        SCREEN_WIDTH / 2,
+        SCREEN_HEIGHT / 2 - 4,
+        TextAlignment::Center,
+        LIGHT_YELLOW,
+        "RUST ROGUELIKE",
+    );
+
+    // there is only a game to continue if one was saved
+    let mut options = vec![("Play a new game", GameState::NewGame)];
+    if save::save_exists() {
+        options.push(("Continue last game", GameState::Continue));
+    }
+    options.push(("Quit", GameState::Quit));
+    let choices: Vec<_> = options.iter().map(|&(name, _)| name).collect();
+    match menu("", &choices, START_MENU_WIDTH, tcod) {
+        Some(index) => options[index].1,
+        None => GameState::MainMenu,
+    }
+}
+
+/// Shows how the run ended and throws its save away.
+fn end_run<F: Frontend>(tcod: &mut Tcod<F>, text: &str) -> GameState {
+    if let Err(e) = save::delete_save() {
+        eprintln!("Could not delete the saved game: {}", e);
+    }
+    msgbox(text, INVENTORY_WIDTH, tcod);
+    GameState::MainMenu
+}
+
+fn save_game<F: Frontend>(tcod: &mut Tcod<F>, game: &Game, objects: &[Object]) {
+    if let Err(e) = save::save_game(game, objects) {
+        msgbox(
+            &format!("\nCould not save the game: {}\n", e),
+            INVENTORY_WIDTH,
+            tcod,
+        );
+    }
+}
+
+/// The experience a character of `level` needs to reach the next level.
+fn level_up_xp(level: i32) -> i32 {
+    LEVEL_UP_BASE + level * LEVEL_UP_FACTOR
+}
+
+/// Whether the player has the experience to level up.
+fn can_level_up(player: &Object) -> bool {
+    match player.fighter {
+        Some(fighter) => player.is_alive && fighter.xp >= level_up_xp(player.level),
+        None => false,
+    }
+}
+
+/// Once the player can level up, asks which stat to raise. Returns None if
+/// they can't, or if the window closed before they chose.
+fn choose_stat<F: Frontend>(tcod: &mut Tcod<F>, objects: &[Object]) -> Option<Stat> {
+    if !can_level_up(&objects[0]) {
+        return None;
+    }
+    let fighter = objects[0].fighter.unwrap();
+    let options = [
+        format!("Constitution (+20 HP, from {})", fighter.base_max_hp),
+        format!("Strength (+1 attack, from {})", fighter.base_power),
+        format!("Agility (+1 defense, from {})", fighter.base_defense),
+    ];
+    let mut choice = None;
+    while choice.is_none() && !tcod.frontend.window_closed() {
+        // keep asking until a choice is made
+        choice = menu(
+            "Level up! Choose a stat to raise:\n",
+            &options,
+            LEVEL_SCREEN_WIDTH,
+            tcod,
+        );
+    }
+    match choice {
+        Some(0) => Some(Stat::Constitution),
+        Some(1) => Some(Stat::Strength),
+        Some(2) => Some(Stat::Agility),
+        // the window closed; level up next time
+        _ => None,
+    }
+}
+
+/// Levels the player up, raising `stat`. Does nothing unless they have the
+/// experience for it.
+fn level_up(stat: Stat, game: &mut Game, objects: &mut [Object]) {
+    if !can_level_up(&objects[0]) {
+        return;
+    }
+    let player = &mut objects[0];
+    let level_up_xp = level_up_xp(player.level);
+    let fighter = player.fighter.as_mut().unwrap();
+    match stat {
+        Stat::Constitution => {
+            fighter.base_max_hp += 20;
+            fighter.hp += 20;
+        }
+        Stat::Strength => fighter.base_power += 1,
+        Stat::Agility => fighter.base_defense += 1,
+    }
+    fighter.xp -= level_up_xp;
+    player.level += 1;
+    game.messages.add(
+        format!(
+            "Your battle skills grow stronger! You reached level {}!",
+            player.level
+        ),
+        YELLOW,
+    );
+}
+
+/// Plays until the player leaves, wins or dies, taking what they do from
+/// `controller`.
+fn play_game<F: Frontend, C: Controller>(
+    tcod: &mut Tcod<F>,
+    game: &mut Game,
+    objects: &mut Vec<Object>,
+    controller: &mut C,
+) -> GameState {
+    initialise_fov(&mut tcod.fov, &game.map);
+    tcod.fov.compute_fov(
+        objects[0].x,
+        objects[0].y,
+        TORCH_RADIUS,
+        FOV_LIGHT_WALLS,
+        FOV_ALGORITHM,
+    );
+    // a fresh game starts with no energy banked, so let time run until the
+    // player can act
+    scheduler::advance(game, objects, |id, game, objects| {
+        ai::ai_take_turn(id, &tcod.fov, game, objects)
+    });
 
     let mut previous_player_position = (-1, -1);
-    game.messages.add("Testing!", RED);
-    while !tcod.root.window_closed() {
-        tcod.con.clear();
-        let fov_recompute = previous_player_position != (objects[0].x, objects[0].y);
-        match input::check_for_event(input::MOUSE | input::KEY_PRESS) {
-            Some((_, Event::Mouse(m))) => tcod.mouse = m,
-            Some((_, Event::Key(k))) => tcod.key = k,
+    while !tcod.frontend.window_closed() {
+        tcod.frontend.screen().con.clear(BLACK);
+        let map_changed = update_fov(&mut tcod.fov, game);
+        let fov_recompute = map_changed || previous_player_position != (objects[0].x, objects[0].y);
+        match tcod.frontend.check_for_event() {
+            Some(Input::Mouse(m)) => tcod.mouse = m,
+            Some(Input::Key(k)) => tcod.key = k,
             _ => tcod.key = Default::default(),
         }
-        render_all(&mut tcod, &mut game, &objects, fov_recompute);
+        render_all(
+            tcod.frontend.screen(),
+            &mut tcod.fov,
+            tcod.mouse,
+            game,
+            objects,
+            fov_recompute,
+        );
+        controller.draw_status(&mut tcod.frontend.screen().root);
 
-        tcod.root.flush();
+        tcod.frontend.flush();
         let player = &mut objects[0];
         previous_player_position = (player.x, player.y);
-        let exit = handle_keys(&mut tcod, 0, &mut objects, &mut game);
-
-        if objects[0].is_alive && exit != PlayerAction::DidntTakeTurn {
-            for id in 0..objects.len() {
-                if objects[id].ai.is_some() {
-                    ai::ai_take_turn(id, &tcod, &mut game, &mut objects)
-                }
+        let exit = match controller.next_input(tcod, game, objects) {
+            PlayerInput::Command(command) => {
+                controller.record(command);
+                command::execute(command, &mut tcod.fov, game, objects)
             }
+            PlayerInput::Exit => PlayerAction::Exit,
+            PlayerInput::Nothing => PlayerAction::DidntTakeTurn,
+        };
+
+        if let PlayerAction::TookTurn(cost) = exit {
+            objects[0].energy -= cost;
+        }
+        // levelling up is a command too, so that replays raise the same stats
+        if let Some(stat) = controller.level_up_choice(tcod, objects) {
+            controller.record(Command::LevelUp(stat));
+            command::execute(Command::LevelUp(stat), &mut tcod.fov, game, objects);
+        }
+        scheduler::advance(game, objects, |id, game, objects| {
+            ai::ai_take_turn(id, &tcod.fov, game, objects)
+        });
+        if let PlayerAction::TookTurn(_) = exit {
+            game.messages.next_turn();
         }
 
-        if exit == PlayerAction::Exit {
-            break;
+        match exit {
+            PlayerAction::Exit => return GameState::MainMenu,
+            PlayerAction::Won => return GameState::Victory,
+            _ => {}
+        }
+
+        if !objects[0].is_alive {
+            // show the corpse one last time before the death screen
+            tcod.frontend.screen().con.clear(BLACK);
+            render_all(
+                tcod.frontend.screen(),
+                &mut tcod.fov,
+                tcod.mouse,
+                game,
+                objects,
+                false,
+            );
+            tcod.frontend.flush();
+            return GameState::Dead;
         }
     }
-    println!("Hello, world!");
+    GameState::Playing
 }
@@ -0,0 +1,637 @@
+use std::collections::VecDeque;
+
+use rand::Rng;
+
+use crate::{
+    create_h_tunnel, create_room, create_v_tunnel,
+    object::Object,
+    place_objects,
+    rng::GameRng,
+    roomgen::Rect,
+    tile::{Tile, TileKind},
+    Map, MAP_HEIGHT, MAP_WIDTH, MAX_ROOMS, ROOM_MAX_SIZE, ROOM_MIN_SIZE,
+};
+
+/// Caves only appear from this level down.
+const CAVES_FROM_LEVEL: u32 = 3;
+/// Chance in percent that a cave tile starts out as wall.
+const CAVE_FILL_PERCENT: u32 = 45;
+const CAVE_SMOOTHING_PASSES: u32 = 5;
+/// A cave gets a room's worth of monsters and items per this many tiles of
+/// floor.
+const CAVE_FLOOR_PER_ROOM: usize = 80;
+/// Caves whose largest open region is smaller than this are generated again.
+const CAVE_MIN_FLOOR: usize = (MAP_WIDTH * MAP_HEIGHT / 3) as usize;
+/// Patches of grass, rubble, water or lava on each level.
+const TERRAIN_PATCHES: u32 = 6;
+/// Lava only appears from this level down.
+const LAVA_FROM_LEVEL: u32 = 4;
+/// Tunnels `connect_unreachable` digs before giving up on a map.
+const MAX_REPAIR_TUNNELS: u32 = 10;
+/// A BSP leaf is only split while both halves would be at least this big...
+const BSP_MIN_LEAF_SIZE: i32 = ROOM_MIN_SIZE + 2;
+/// ...and while it is wider or taller than this.
+const BSP_MAX_LEAF_SIZE: i32 = 20;
+
+/// A freshly generated level: the map, plus where the player arrives and
+/// where the stairs down go.
+pub struct Layout {
+    pub map: Map,
+    pub start: (i32, i32),
+    pub exit: (i32, i32),
+}
+
+/// A way of generating levels. Generators carve the map and place the
+/// monsters and items; `make_map` adds the player and the stairs.
+pub trait MapGenerator {
+    fn generate(&self, objects: &mut Vec<Object>, level: u32, rng: &mut GameRng) -> Layout;
+}
+
+/// Picks the algorithm for a new level.
+pub fn generator_for(level: u32, rng: &mut GameRng) -> Box<dyn MapGenerator> {
+    let choices = if level >= CAVES_FROM_LEVEL { 3 } else { 2 };
+    match rng.gen_range(0, choices) {
+        0 => Box::new(Bsp),
+        1 => Box::new(RandomRooms),
+        _ => Box::new(Caves),
+    }
+}
+
+/// The open tiles that can be walked to from `start`, nearest first. Doors
+/// count as open, since they can be opened on the way; deep water and lava
+/// do not.
+pub fn flood_fill(map: &Map, start: (i32, i32)) -> Vec<(i32, i32)> {
+    let mut seen = vec![vec![false; MAP_HEIGHT as usize]; MAP_WIDTH as usize];
+    let mut queue = VecDeque::from(vec![start]);
+    let mut reached = vec![];
+    seen[start.0 as usize][start.1 as usize] = true;
+    while let Some((x, y)) = queue.pop_front() {
+        reached.push((x, y));
+        for (dx, dy) in [(0, -1), (1, 0), (0, 1), (-1, 0)] {
+            let (x, y) = (x + dx, y + dy);
+            if x < 0 || y < 0 || x >= MAP_WIDTH || y >= MAP_HEIGHT {
+                continue;
+            }
+            let tile = &map[x as usize][y as usize];
+            let passable = (!tile.blocked || tile.kind == TileKind::Door) && tile.kind.is_safe();
+            if passable && !seen[x as usize][y as usize] {
+                seen[x as usize][y as usize] = true;
+                queue.push_back((x, y));
+            }
+        }
+    }
+    reached
+}
+
+/// Rooms dropped at random, skipping any that would overlap one already
+/// placed, each joined to the previous one.
+pub struct RandomRooms;
+
+impl RandomRooms {
+    /// Carves the rooms and their tunnels into `map`, returning the rooms in
+    /// the order they were connected.
+    pub fn carve(&self, map: &mut Map, rng: &mut GameRng) -> Vec<Rect> {
+        let mut rooms: Vec<Rect> = vec![];
+        for _ in 0..MAX_ROOMS {
+            let w = rng.gen_range(ROOM_MIN_SIZE, ROOM_MAX_SIZE + 1);
+            let h = rng.gen_range(ROOM_MIN_SIZE, ROOM_MAX_SIZE + 1);
+            let x = rng.gen_range(0, MAP_WIDTH - w);
+            let y = rng.gen_range(0, MAP_HEIGHT - h);
+            let new_room = Rect::new(x, y, w, h);
+            let failed = rooms
+                .iter()
+                .any(|other_room| new_room.intersects_with(other_room));
+            if !failed {
+                create_room(new_room.clone(), map);
+                if let Some(previous) = rooms.last() {
+                    connect(previous.center(), new_room.center(), map, rng);
+                }
+                rooms.push(new_room);
+            }
+        }
+        rooms
+    }
+}
+
+impl MapGenerator for RandomRooms {
+    fn generate(&self, objects: &mut Vec<Object>, level: u32, rng: &mut GameRng) -> Layout {
+        let mut map = vec![vec![Tile::wall(); MAP_HEIGHT as usize]; MAP_WIDTH as usize];
+        let rooms = self.carve(&mut map, rng);
+        furnish_rooms(map, &rooms, objects, level, rng)
+    }
+}
+
+/// Binary space partitioning: the map is split in two again and again, a
+/// room is carved in every leaf and the two halves of each split are joined
+/// by a tunnel, so no space is wasted on rooms that did not fit.
+pub struct Bsp;
+
+impl Bsp {
+    /// Carves the rooms and their tunnels into `map`, returning the rooms
+    /// from left to right and top to bottom in the partition.
+    pub fn carve(&self, map: &mut Map, rng: &mut GameRng) -> Vec<Rect> {
+        let mut rooms = vec![];
+        // the right and bottom edges stay wall, like a room's
+        let whole_map = Rect::new(0, 0, MAP_WIDTH - 1, MAP_HEIGHT - 1);
+        split(&whole_map, map, &mut rooms, rng);
+        rooms
+    }
+}
+
+impl MapGenerator for Bsp {
+    fn generate(&self, objects: &mut Vec<Object>, level: u32, rng: &mut GameRng) -> Layout {
+        let mut map = vec![vec![Tile::wall(); MAP_HEIGHT as usize]; MAP_WIDTH as usize];
+        let rooms = self.carve(&mut map, rng);
+        furnish_rooms(map, &rooms, objects, level, rng)
+    }
+}
+
+/// Everything on a map the player cannot walk to from where they stand.
+#[derive(Debug, Default, PartialEq)]
+pub struct Unreachable {
+    /// Open areas cut off from the player, tile by tile.
+    pub regions: Vec<Vec<(i32, i32)>>,
+    /// Objects in those areas or stuck inside walls, by index.
+    pub objects: Vec<usize>,
+}
+
+impl Unreachable {
+    pub fn is_empty(&self) -> bool {
+        self.regions.is_empty() && self.objects.is_empty()
+    }
+}
+
+/// Flood-fills from the player at `objects[0]` and reports what it misses.
+pub fn find_unreachable(map: &Map, objects: &[Object]) -> Unreachable {
+    let mut reached = vec![vec![false; MAP_HEIGHT as usize]; MAP_WIDTH as usize];
+    for (x, y) in flood_fill(map, objects[0].pos()) {
+        reached[x as usize][y as usize] = true;
+    }
+    let cut_off_objects = (1..objects.len())
+        .filter(|&id| {
+            let (x, y) = objects[id].pos();
+            !reached[x as usize][y as usize]
+        })
+        .collect();
+
+    let mut regions = vec![];
+    for x in 0..MAP_WIDTH {
+        for y in 0..MAP_HEIGHT {
+            let tile = &map[x as usize][y as usize];
+            if tile.blocked || !tile.kind.is_safe() || reached[x as usize][y as usize] {
+                continue;
+            }
+            let region = flood_fill(map, (x, y));
+            for &(x, y) in &region {
+                reached[x as usize][y as usize] = true;
+            }
+            regions.push(region);
+        }
+    }
+    Unreachable {
+        regions: regions,
+        objects: cut_off_objects,
+    }
+}
+
+/// Digs a tunnel from the player's area to each cut-off region and to
+/// anything stuck in a wall, until everything is reachable. Returns false
+/// if that still is not the case after `MAX_REPAIR_TUNNELS` tunnels.
+pub fn connect_unreachable(map: &mut Map, objects: &[Object], rng: &mut GameRng) -> bool {
+    for _ in 0..MAX_REPAIR_TUNNELS {
+        let unreachable = find_unreachable(map, objects);
+        let cut_off = match (unreachable.regions.first(), unreachable.objects.first()) {
+            (Some(region), _) => region[0],
+            (None, Some(&id)) => objects[id].pos(),
+            (None, None) => return true,
+        };
+        let distance = |&(x, y): &(i32, i32)| (x - cut_off.0).abs() + (y - cut_off.1).abs();
+        let nearest = flood_fill(map, objects[0].pos())
+            .into_iter()
+            .min_by_key(distance)
+            .unwrap();
+        connect(nearest, cut_off, map, rng);
+    }
+    find_unreachable(map, objects).is_empty()
+}
+
+/// Cellular automata caves: the map starts as random noise and is smoothed
+/// until walls and floor clump together, then every open region but the
+/// largest is filled in.
+pub struct Caves;
+
+impl Caves {
+    /// Carves a cave into `map`, returning its floor tiles.
+    pub fn carve(&self, map: &mut Map, rng: &mut GameRng) -> Vec<(i32, i32)> {
+        loop {
+            for x in 0..MAP_WIDTH {
+                for y in 0..MAP_HEIGHT {
+                    let wall = is_edge(x, y) || rng.gen_range(0, 100) < CAVE_FILL_PERCENT;
+                    map[x as usize][y as usize] = if wall { Tile::wall() } else { Tile::empty() };
+                }
+            }
+            for _ in 0..CAVE_SMOOTHING_PASSES {
+                smooth(map);
+            }
+
+            let mut largest: Vec<(i32, i32)> = vec![];
+            let mut seen = vec![vec![false; MAP_HEIGHT as usize]; MAP_WIDTH as usize];
+            for x in 0..MAP_WIDTH {
+                for y in 0..MAP_HEIGHT {
+                    if map[x as usize][y as usize].blocked || seen[x as usize][y as usize] {
+                        continue;
+                    }
+                    let region = flood_fill(map, (x, y));
+                    for &(x, y) in &region {
+                        seen[x as usize][y as usize] = true;
+                    }
+                    if region.len() > largest.len() {
+                        largest = region;
+                    }
+                }
+            }
+            if largest.len() < CAVE_MIN_FLOOR {
+                continue;
+            }
+
+            let mut keep = vec![vec![false; MAP_HEIGHT as usize]; MAP_WIDTH as usize];
+            for &(x, y) in &largest {
+                keep[x as usize][y as usize] = true;
+            }
+            for x in 0..MAP_WIDTH {
+                for y in 0..MAP_HEIGHT {
+                    if !keep[x as usize][y as usize] {
+                        map[x as usize][y as usize] = Tile::wall();
+                    }
+                }
+            }
+            return largest;
+        }
+    }
+}
+
+impl MapGenerator for Caves {
+    fn generate(&self, objects: &mut Vec<Object>, level: u32, rng: &mut GameRng) -> Layout {
+        let mut map = vec![vec![Tile::wall(); MAP_HEIGHT as usize]; MAP_WIDTH as usize];
+        let floor = self.carve(&mut map, rng);
+        add_terrain(&mut map, level, rng);
+        let start = floor[rng.gen_range(0, floor.len())];
+        map[start.0 as usize][start.1 as usize] = Tile::empty();
+        // with the player standing on the start, nothing spawns on top of them
+        objects[0].set_pos(start.0, start.1);
+        for _ in 0..floor.len() / CAVE_FLOOR_PER_ROOM {
+            let random_spot = |rng: &mut GameRng| floor[rng.gen_range(0, floor.len())];
+            place_objects(random_spot, &map, objects, level, rng);
+        }
+
+        // the way down is as far from the start as the cave allows
+        let exit = *flood_fill(&map, start).last().unwrap();
+        Layout {
+            map: map,
+            start: start,
+            exit: exit,
+        }
+    }
+}
+
+fn is_edge(x: i32, y: i32) -> bool {
+    x == 0 || y == 0 || x == MAP_WIDTH - 1 || y == MAP_HEIGHT - 1
+}
+
+/// One smoothing pass: tiles surrounded mostly by wall become wall, tiles
+/// with few walls around them open up. The edges always stay wall.
+fn smooth(map: &mut Map) {
+    let before = map.clone();
+    for x in 1..MAP_WIDTH - 1 {
+        for y in 1..MAP_HEIGHT - 1 {
+            let mut walls = 0;
+            for dx in -1..=1 {
+                for dy in -1..=1 {
+                    if (dx, dy) != (0, 0) && before[(x + dx) as usize][(y + dy) as usize].blocked {
+                        walls += 1;
+                    }
+                }
+            }
+            if walls >= 5 {
+                map[x as usize][y as usize] = Tile::wall();
+            } else if walls <= 3 {
+                map[x as usize][y as usize] = Tile::empty();
+            }
+        }
+    }
+}
+
+/// Splits `leaf` or carves a room in it, returning the centre of one of the
+/// rooms inside it for the caller to connect to.
+fn split(leaf: &Rect, map: &mut Map, rooms: &mut Vec<Rect>, rng: &mut GameRng) -> (i32, i32) {
+    let width = leaf.x2 - leaf.x1;
+    let height = leaf.y2 - leaf.y1;
+    let can_split_x = width >= 2 * BSP_MIN_LEAF_SIZE;
+    let can_split_y = height >= 2 * BSP_MIN_LEAF_SIZE;
+    let too_big = width > BSP_MAX_LEAF_SIZE || height > BSP_MAX_LEAF_SIZE;
+
+    if !too_big || !(can_split_x || can_split_y) {
+        let w = rng.gen_range(ROOM_MIN_SIZE, ROOM_MAX_SIZE.min(width) + 1);
+        let h = rng.gen_range(ROOM_MIN_SIZE, ROOM_MAX_SIZE.min(height) + 1);
+        let x = rng.gen_range(leaf.x1, leaf.x2 - w + 1);
+        let y = rng.gen_range(leaf.y1, leaf.y2 - h + 1);
+        let room = Rect::new(x, y, w, h);
+        create_room(room.clone(), map);
+        let center = room.center();
+        rooms.push(room);
+        return center;
+    }
+
+    // cut across the longer side, so leaves stay roughly square
+    let split_x = if can_split_x && can_split_y {
+        width >= height
+    } else {
+        can_split_x
+    };
+    let (first, second) = if split_x {
+        let at = rng.gen_range(leaf.x1 + BSP_MIN_LEAF_SIZE, leaf.x2 - BSP_MIN_LEAF_SIZE + 1);
+        (
+            Rect::new(leaf.x1, leaf.y1, at - leaf.x1, height),
+            Rect::new(at, leaf.y1, leaf.x2 - at, height),
+        )
+    } else {
+        let at = rng.gen_range(leaf.y1 + BSP_MIN_LEAF_SIZE, leaf.y2 - BSP_MIN_LEAF_SIZE + 1);
+        (
+            Rect::new(leaf.x1, leaf.y1, width, at - leaf.y1),
+            Rect::new(leaf.x1, at, width, leaf.y2 - at),
+        )
+    };
+    let a = split(&first, map, rooms, rng);
+    let b = split(&second, map, rooms, rng);
+    connect(a, b, map, rng);
+    if rng.gen() {
+        a
+    } else {
+        b
+    }
+}
+
+/// Joins two points with an L-shaped tunnel, bending one way or the other.
+fn connect(from: (i32, i32), to: (i32, i32), map: &mut Map, rng: &mut GameRng) {
+    let ((from_x, from_y), (to_x, to_y)) = (from, to);
+    if rng.gen() {
+        create_h_tunnel(from_x, to_x, from_y, map);
+        create_v_tunnel(from_y, to_y, to_x, map);
+    } else {
+        create_v_tunnel(from_y, to_y, from_x, map);
+        create_h_tunnel(from_x, to_x, to_y, map);
+    }
+}
+
+/// Puts a door wherever a tunnel goes through the room's wall. A tunnel that
+/// runs along the wall leaves no wall on either side of its tiles, so it
+/// gets no doors.
+fn add_doors(room: &Rect, map: &mut Map) {
+    let doorway = |x: i32, y: i32, (dx, dy): (i32, i32), map: &mut Map| {
+        let blocked = |x: i32, y: i32| map[x as usize][y as usize].blocked;
+        if !blocked(x, y) && blocked(x - dx, y - dy) && blocked(x + dx, y + dy) {
+            map[x as usize][y as usize] = Tile::door();
+        }
+    };
+    for x in room.x1 + 1..room.x2 {
+        doorway(x, room.y1, (1, 0), map);
+        doorway(x, room.y2, (1, 0), map);
+    }
+    for y in room.y1 + 1..room.y2 {
+        doorway(room.x1, y, (0, 1), map);
+        doorway(room.x2, y, (0, 1), map);
+    }
+}
+
+/// Fills the rooms with monsters and items. The player starts in the first
+/// room and the way down is in the last.
+fn furnish_rooms(
+    mut map: Map,
+    rooms: &[Rect],
+    objects: &mut Vec<Object>,
+    level: u32,
+    rng: &mut GameRng,
+) -> Layout {
+    for room in rooms {
+        add_doors(room, &mut map);
+    }
+    add_terrain(&mut map, level, rng);
+    let start = rooms[0].center();
+    let exit = rooms[rooms.len() - 1].center();
+    for (x, y) in [start, exit] {
+        map[x as usize][y as usize] = Tile::empty();
+    }
+    // with the player standing on the start, nothing spawns on top of them
+    objects[0].set_pos(start.0, start.1);
+    for room in rooms {
+        place_objects(|rng| room.random_tile(rng), &map, objects, level, rng);
+    }
+    Layout {
+        map: map,
+        start: start,
+        exit: exit,
+    }
+}
+
+/// Scatters round patches of grass, rubble, water and, deeper down, lava
+/// over the floor. Big enough pools of water are deep in the middle.
+fn add_terrain(map: &mut Map, level: u32, rng: &mut GameRng) {
+    use TileKind::*;
+    let kinds: &[TileKind] = if level >= LAVA_FROM_LEVEL {
+        &[Grass, Rubble, Water, Lava]
+    } else {
+        &[Grass, Rubble, Water]
+    };
+    let floor: Vec<(i32, i32)> = (0..MAP_WIDTH)
+        .flat_map(|x| (0..MAP_HEIGHT).map(move |y| (x, y)))
+        .filter(|&(x, y)| map[x as usize][y as usize].kind == Floor)
+        .collect();
+    if floor.is_empty() {
+        return;
+    }
+    for _ in 0..TERRAIN_PATCHES {
+        let kind = kinds[rng.gen_range(0, kinds.len())];
+        let radius = rng.gen_range(1, 4);
+        let (center_x, center_y) = floor[rng.gen_range(0, floor.len())];
+        for x in center_x - radius..=center_x + radius {
+            for y in center_y - radius..=center_y + radius {
+                let distance = (x - center_x).pow(2) + (y - center_y).pow(2);
+                if is_edge(x, y) || x < 0 || y < 0 || x >= MAP_WIDTH || y >= MAP_HEIGHT {
+                    continue;
+                }
+                if distance > radius.pow(2) || map[x as usize][y as usize].kind != Floor {
+                    continue;
+                }
+                let deep = kind == Water && radius >= 3 && distance <= (radius - 2).pow(2);
+                map[x as usize][y as usize] = Tile::new(if deep { DeepWater } else { kind });
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tcod::colors::WHITE;
+
+    use super::*;
+    use crate::{build_level, dungeon, make_empty_map, new_game};
+
+    #[test]
+    fn bsp_rooms_fill_the_map_without_overlapping_and_all_connect() {
+        for seed in 0..20 {
+            let mut rng = GameRng::new(seed);
+            let mut map = make_empty_map();
+            for column in map.iter_mut() {
+                column.fill(Tile::wall());
+            }
+            let rooms = Bsp.carve(&mut map, &mut rng);
+            assert!(
+                rooms.len() >= 8,
+                "only {} rooms with seed {}",
+                rooms.len(),
+                seed
+            );
+
+            for (i, room) in rooms.iter().enumerate() {
+                assert!(room.x1 >= 0 && room.y1 >= 0);
+                assert!(room.x2 < MAP_WIDTH && room.y2 < MAP_HEIGHT);
+                for other in &rooms[i + 1..] {
+                    let inner = Rect::new(
+                        room.x1 + 1,
+                        room.y1 + 1,
+                        room.x2 - room.x1 - 2,
+                        room.y2 - room.y1 - 2,
+                    );
+                    let other_inner = Rect::new(
+                        other.x1 + 1,
+                        other.y1 + 1,
+                        other.x2 - other.x1 - 2,
+                        other.y2 - other.y1 - 2,
+                    );
+                    assert!(
+                        !inner.intersects_with(&other_inner),
+                        "{:?} overlaps {:?}",
+                        room,
+                        other
+                    );
+                }
+            }
+            let walkable = flood_fill(&map, rooms[0].center());
+            assert!(rooms.iter().all(|room| walkable.contains(&room.center())));
+        }
+    }
+
+    #[test]
+    fn every_generator_places_the_player_and_the_way_down() {
+        let generators: [&dyn MapGenerator; 3] = [&RandomRooms, &Bsp, &Caves];
+        for generator in generators {
+            let mut objects = vec![Object::new(0, 0, '@', WHITE, "me".to_string(), true, true)];
+            let layout = generator.generate(&mut objects, 3, &mut GameRng::new(5));
+            let walkable = flood_fill(&layout.map, layout.start);
+            assert!(walkable.contains(&layout.exit));
+            assert!(objects[1..].iter().all(|o| {
+                let tile = layout.map[o.x as usize][o.y as usize];
+                !tile.blocked && tile.kind.is_safe()
+            }));
+        }
+    }
+
+    #[test]
+    fn nothing_blocking_spawns_on_the_start_or_on_top_of_something_else() {
+        let generators: [&dyn MapGenerator; 3] = [&RandomRooms, &Bsp, &Caves];
+        for generator in generators {
+            for seed in 0..20 {
+                let mut objects = vec![Object::new(0, 0, '@', WHITE, "me".to_string(), true, true)];
+                let layout = generator.generate(&mut objects, 6, &mut GameRng::new(seed));
+                let mut taken = vec![layout.start];
+                for object in objects[1..].iter().filter(|o| o.blocks_motion) {
+                    assert!(!taken.contains(&object.pos()), "seed {}", seed);
+                    taken.push(object.pos());
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn every_cave_tile_can_be_reached_from_the_start() {
+        for seed in 0..10 {
+            let mut objects = vec![Object::new(0, 0, '@', WHITE, "me".to_string(), true, true)];
+            let mut rng = GameRng::new(seed);
+            let map = loop {
+                if let Some(map) = build_level(&Caves, &mut objects, 4, &mut rng) {
+                    break map;
+                }
+            };
+            let reached = flood_fill(&map, objects[0].pos());
+            let floor = (0..MAP_WIDTH)
+                .flat_map(|x| (0..MAP_HEIGHT).map(move |y| (x, y)))
+                .filter(|&(x, y)| {
+                    let tile = map[x as usize][y as usize];
+                    !tile.blocked && tile.kind.is_safe()
+                })
+                .count();
+            assert_eq!(reached.len(), floor, "seed {}", seed);
+            assert!(objects.len() > 2, "seed {} spawned nothing", seed);
+            assert!(objects[1..].iter().all(|o| reached.contains(&o.pos())));
+        }
+    }
+
+    #[test]
+    fn cut_off_rooms_and_objects_are_found_and_tunnelled_to() {
+        let mut map = vec![vec![Tile::wall(); MAP_HEIGHT as usize]; MAP_WIDTH as usize];
+        create_room(Rect::new(2, 2, 8, 8), &mut map);
+        create_room(Rect::new(40, 20, 8, 8), &mut map);
+        let player = Object::new(5, 5, '@', WHITE, "me".to_string(), true, true);
+        let potion = Object::new(44, 24, '!', WHITE, "potion".to_string(), false, true);
+        let buried = Object::new(60, 5, '/', WHITE, "sword".to_string(), false, true);
+        let objects = vec![player, potion, buried];
+
+        let unreachable = find_unreachable(&map, &objects);
+        assert_eq!(unreachable.regions.len(), 1);
+        assert_eq!(unreachable.regions[0].len(), 7 * 7);
+        assert_eq!(unreachable.objects, vec![1, 2]);
+
+        assert!(connect_unreachable(
+            &mut map,
+            &objects,
+            &mut GameRng::new(3)
+        ));
+        assert!(find_unreachable(&map, &objects).is_empty());
+    }
+
+    #[test]
+    fn generated_levels_are_fully_connected() {
+        for seed in 0..10 {
+            let (mut game, mut objects) = new_game(seed);
+            for level in 1..=5 {
+                if level > 1 {
+                    dungeon::enter_level(&mut game, &mut objects, level);
+                }
+                let unreachable = find_unreachable(&game.map, &objects);
+                assert!(unreachable.is_empty(), "seed {} level {}", seed, level);
+            }
+        }
+    }
+
+    #[test]
+    fn doors_are_placed_where_tunnels_enter_rooms() {
+        let mut doors = 0;
+        for seed in 0..5 {
+            let mut objects = vec![Object::new(0, 0, '@', WHITE, "me".to_string(), true, true)];
+            let layout = RandomRooms.generate(&mut objects, 1, &mut GameRng::new(seed));
+            let map = &layout.map;
+            for x in 1..MAP_WIDTH as usize - 1 {
+                for y in 1..MAP_HEIGHT as usize - 1 {
+                    if map[x][y].kind == TileKind::Door {
+                        doors += 1;
+                        assert!(map[x][y].is_closed_door());
+                        let walls_across = map[x - 1][y].blocked && map[x + 1][y].blocked;
+                        let walls_along = map[x][y - 1].blocked && map[x][y + 1].blocked;
+                        assert!(walls_across || walls_along, "door at {:?}", (x, y));
+                    }
+                }
+            }
+        }
+        assert!(doors > 0);
+    }
+}
@@ -1,19 +1,134 @@
+use std::{borrow::Cow, collections::VecDeque};
+
+use serde::{Deserialize, Serialize};
 use tcod::Color;
 
+use crate::MAX_MESSAGES;
+
+/// One entry of the message log.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Message {
+    pub text: String,
+    pub colour: Color,
+    /// The player turn the message was last logged on.
+    pub turn: u32,
+    /// How many times in a row it was logged.
+    pub count: u32,
+}
+
+impl Message {
+    /// The text as shown, with a counter if it was repeated.
+    pub fn line(&self) -> Cow<str> {
+        if self.count > 1 {
+            Cow::Owned(format!("{} (x{})", self.text, self.count))
+        } else {
+            Cow::Borrowed(&self.text)
+        }
+    }
+}
+
+/// The message log. Only the last `MAX_MESSAGES` entries are kept, and a
+/// message logged several times in a row takes up a single entry.
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
 pub struct Messages {
-    messages: Vec<(String, Color)>,
+    messages: VecDeque<Message>,
+    /// The turn new messages are stamped with.
+    turn: u32,
 }
 
 impl Messages {
     pub fn new() -> Self {
-        Self { messages: vec![] }
+        Self {
+            messages: VecDeque::new(),
+            turn: 1,
+        }
     }
 
     pub fn add<T: Into<String>>(&mut self, message: T, colour: Color) {
-        self.messages.push((message.into(), colour));
+        let text = message.into();
+        if let Some(last) = self.messages.back_mut() {
+            if last.text == text && last.colour == colour {
+                last.count += 1;
+                last.turn = self.turn;
+                return;
+            }
+        }
+        self.messages.push_back(Message {
+            text: text,
+            colour: colour,
+            turn: self.turn,
+            count: 1,
+        });
+        while self.messages.len() > MAX_MESSAGES {
+            self.messages.pop_front();
+        }
     }
 
-    pub fn iter(&self) -> impl DoubleEndedIterator<Item = &(String, Color)> {
+    pub fn iter(&self) -> impl DoubleEndedIterator<Item = &Message> {
         self.messages.iter()
     }
+
+    pub fn len(&self) -> usize {
+        self.messages.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.messages.is_empty()
+    }
+
+    pub fn turn(&self) -> u32 {
+        self.turn
+    }
+
+    /// Called once the player's turn and everything it set off are over, so
+    /// that what happens next is stamped with the following one.
+    pub fn next_turn(&mut self) {
+        self.turn += 1;
+    }
+
+    /// The messages containing `search`, ignoring case, oldest first.
+    pub fn matching(&self, search: &str) -> Vec<&Message> {
+        let search = search.to_lowercase();
+        self.iter()
+            .filter(|message| message.text.to_lowercase().contains(&search))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tcod::colors::WHITE;
+
+    use super::*;
+
+    #[test]
+    fn message_log_is_capped_and_stamped_with_turns() {
+        let mut messages = Messages::new();
+        messages.add("Welcome!", WHITE);
+        messages.next_turn();
+        for n in 0..MAX_MESSAGES {
+            messages.add(format!("Message {}", n), WHITE);
+        }
+
+        assert_eq!(messages.len(), MAX_MESSAGES);
+        let oldest = messages.iter().next().unwrap();
+        assert_eq!((oldest.text.as_str(), oldest.turn), ("Message 0", 2));
+        assert_eq!(messages.matching("MESSAGE 99").len(), 11);
+    }
+
+    #[test]
+    fn repeated_messages_share_one_entry() {
+        let mut messages = Messages::new();
+        for _ in 0..3 {
+            messages.add("The orc hits you.", WHITE);
+        }
+        messages.next_turn();
+        messages.add("The orc hits you.", WHITE);
+        messages.add("You hit the orc.", WHITE);
+
+        assert_eq!(messages.len(), 2);
+        let repeated = messages.iter().next().unwrap();
+        assert_eq!(repeated.line(), "The orc hits you. (x4)");
+        assert_eq!(repeated.turn, 2);
+    }
 }
@@ -1,12 +1,19 @@
-use tcod::{colors::WHITE, Color, Console};
+use serde::{Deserialize, Serialize};
+use tcod::{
+    colors::{LIGHT_GREEN, WHITE, YELLOW},
+    Color,
+};
 
 use crate::{
-    components,
+    components::{self, Equipment},
     game::{self, Game},
-    is_blocked, Map,
+    is_blocked,
+    messages::Messages,
+    render::Canvas,
+    Map,
 };
 
-#[derive(Debug)]
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
 pub struct Object {
     pub x: i32,
     pub y: i32,
@@ -15,9 +22,20 @@ pub struct Object {
     pub name: String,
     pub blocks_motion: bool,
     pub is_alive: bool,
+    pub always_visible: bool,
+    /// Swimmers can cross deep water.
+    pub swims: bool,
+    /// Accumulated time units, spent by taking actions.
+    pub energy: i32,
+    /// Character level; only the player gains levels.
+    pub level: i32,
     pub fighter: Option<components::Fighter>,
     pub ai: Option<components::Ai>,
     pub item: Option<components::Item>,
+    pub stairs: Option<components::Stairs>,
+    pub equipment: Option<Equipment>,
+    /// The equipment this object has on, which its stats are derived from.
+    pub worn: Vec<Equipment>,
 }
 
 impl Object {
@@ -38,9 +56,16 @@ impl Object {
             name: name,
             blocks_motion: blocks_motion,
             is_alive: is_alive,
+            always_visible: false,
+            swims: false,
+            energy: 0,
+            level: 1,
             fighter: None,
             ai: None,
             item: None,
+            stairs: None,
+            equipment: None,
+            worn: vec![],
         }
     }
 
@@ -53,9 +78,8 @@ impl Object {
         self.y = y;
     }
 
-    pub fn draw(&self, con: &mut dyn Console) {
-        con.set_default_foreground(self.color);
-        con.put_char(self.x, self.y, self.char, tcod::BackgroundFlag::None);
+    pub fn draw(&self, con: &mut dyn Canvas) {
+        con.put_char(self.x, self.y, self.char, self.color);
     }
 
     pub fn distance_to(&self, other: &Object) -> f32 {
@@ -64,7 +88,13 @@ impl Object {
         ((dx.pow(2) + dy.pow(2)) as f32).sqrt()
     }
 
-    pub fn take_damage(&mut self, damage: i32, game: &mut Game) {
+    /// The distance to the tile at (x, y).
+    pub fn distance(&self, x: i32, y: i32) -> f32 {
+        (((x - self.x).pow(2) + (y - self.y).pow(2)) as f32).sqrt()
+    }
+
+    /// Returns the experience the object was worth if the damage killed it.
+    pub fn take_damage(&mut self, damage: i32, game: &mut Game) -> Option<i32> {
         if let Some(fighter) = self.fighter.as_mut() {
             if damage > 0 {
                 fighter.hp -= damage;
@@ -73,13 +103,83 @@ impl Object {
         if let Some(fighter) = self.fighter {
             if fighter.hp <= 0 {
                 self.is_alive = false;
-                fighter.on_death.callback(self, game)
+                fighter.on_death.callback(self, game);
+                return Some(fighter.xp);
+            }
+        }
+        None
+    }
+
+    /// Heals this object up to its maximum HP.
+    pub fn heal(&mut self, amount: i32) {
+        let max_hp = self.max_hp();
+        if let Some(ref mut fighter) = self.fighter {
+            fighter.hp += amount;
+            if fighter.hp > max_hp {
+                fighter.hp = max_hp;
+            }
+        }
+    }
+
+    pub fn power(&self) -> i32 {
+        let base_power = self.fighter.map_or(0, |f| f.base_power);
+        let bonus: i32 = self.worn.iter().map(|e| e.power_bonus).sum();
+        base_power + bonus
+    }
+
+    pub fn defense(&self) -> i32 {
+        let base_defense = self.fighter.map_or(0, |f| f.base_defense);
+        let bonus: i32 = self.worn.iter().map(|e| e.defense_bonus).sum();
+        base_defense + bonus
+    }
+
+    pub fn max_hp(&self) -> i32 {
+        let base_max_hp = self.fighter.map_or(0, |f| f.base_max_hp);
+        let bonus: i32 = self.worn.iter().map(|e| e.max_hp_bonus).sum();
+        base_max_hp + bonus
+    }
+
+    /// Puts this item on `wearer`.
+    pub fn equip(&mut self, wearer: &mut Object, messages: &mut Messages) {
+        if let Some(ref mut equipment) = self.equipment {
+            if !equipment.equipped {
+                equipment.equipped = true;
+                wearer.worn.push(*equipment);
+                messages.add(
+                    format!("Equipped {} on {}.", self.name, equipment.slot),
+                    LIGHT_GREEN,
+                );
+            }
+        } else {
+            messages.add(format!("The {} cannot be equipped.", self.name), WHITE);
+        }
+    }
+
+    /// Takes this item off `wearer`.
+    pub fn unequip(&mut self, wearer: &mut Object, messages: &mut Messages) {
+        if let Some(ref mut equipment) = self.equipment {
+            if equipment.equipped {
+                equipment.equipped = false;
+                // only one item is worn in each slot
+                wearer.worn.retain(|worn| worn.slot != equipment.slot);
+                // losing a max HP bonus can leave the wearer with more HP
+                // than allowed
+                let max_hp = wearer.max_hp();
+                if let Some(fighter) = wearer.fighter.as_mut() {
+                    fighter.hp = fighter.hp.min(max_hp);
+                }
+                messages.add(
+                    format!("Unequipped {} from {}.", self.name, equipment.slot),
+                    YELLOW,
+                );
             }
+        } else {
+            messages.add(format!("The {} cannot be unequipped.", self.name), WHITE);
         }
     }
 
     pub fn attack(&mut self, target: &mut Object, game: &mut Game) {
-        let damage = self.fighter.map_or(0, |f| f.power) - target.fighter.map_or(0, |f| f.defense);
+        let damage = self.power() - target.defense();
         if damage > 0 {
             game.messages.add(
                 format!(
@@ -88,7 +188,12 @@ impl Object {
                 ),
                 WHITE,
             );
-            target.take_damage(damage, game);
+            if let Some(xp) = target.take_damage(damage, game) {
+                // the kill is credited to the attacker
+                if let Some(fighter) = self.fighter.as_mut() {
+                    fighter.xp += xp;
+                }
+            }
         } else {
             game.messages.add(
                 format!(
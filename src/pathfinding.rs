@@ -0,0 +1,252 @@
+use std::{
+    cmp::{self, Reverse},
+    collections::{BinaryHeap, HashMap},
+};
+
+use crate::{
+    game::Game, is_blocked_for, move_by, move_towards, object::Object, Map, ACTION_COST,
+    MAX_PATH_LENGTH,
+};
+
+/// Finds the quickest 8-way path from `start` to `goal` around walls,
+/// blocking objects and, unless the walker `swims`, deep water. Slow ground
+/// counts as several steps (see `TileKind::path_cost`). The returned steps
+/// exclude `start` and end on `goal`, which may itself be occupied (e.g. by
+/// the player being chased). Gives up once every open route takes longer than
+/// `max_length` steps on open floor would.
+pub fn astar(
+    start: (i32, i32),
+    goal: (i32, i32),
+    map: &Map,
+    objects: &Vec<Object>,
+    swims: bool,
+    max_length: usize,
+) -> Option<Vec<(i32, i32)>> {
+    let width = map.len() as i32;
+    let height = map.first().map_or(0, |column| column.len()) as i32;
+    // costs are in energy, and no step costs less than a normal action
+    let step_cost = ACTION_COST as usize;
+    let max_cost = max_length * step_cost;
+    let heuristic =
+        |(x, y): (i32, i32)| cmp::max((goal.0 - x).abs(), (goal.1 - y).abs()) as usize * step_cost;
+
+    let mut open = BinaryHeap::new();
+    let mut came_from = HashMap::new();
+    let mut cost_so_far = HashMap::new();
+    open.push(Reverse((heuristic(start), start)));
+    cost_so_far.insert(start, 0);
+
+    while let Some(Reverse((_, current))) = open.pop() {
+        if current == goal {
+            let mut path = vec![current];
+            let mut step = current;
+            while let Some(&previous) = came_from.get(&step) {
+                if previous == start {
+                    break;
+                }
+                path.push(previous);
+                step = previous;
+            }
+            path.reverse();
+            return Some(path);
+        }
+
+        let cost_here = cost_so_far[&current];
+        if cost_here >= max_cost {
+            continue;
+        }
+        for dx in -1..=1 {
+            for dy in -1..=1 {
+                let next = (current.0 + dx, current.1 + dy);
+                if next == current
+                    || next.0 < 0
+                    || next.1 < 0
+                    || next.0 >= width
+                    || next.1 >= height
+                {
+                    continue;
+                }
+                // closed doors are opened on the way, so they do not block
+                let tile = &map[next.0 as usize][next.1 as usize];
+                let door = tile.is_closed_door();
+                if next != goal && !door && is_blocked_for(swims, next.0, next.1, map, objects) {
+                    continue;
+                }
+                if !door && !tile.walkable_by(swims) {
+                    continue;
+                }
+                let cost = cost_here + tile.kind.path_cost();
+                if cost > max_cost {
+                    continue;
+                }
+                if cost_so_far.get(&next).map_or(true, |&known| cost < known) {
+                    cost_so_far.insert(next, cost);
+                    came_from.insert(next, current);
+                    open.push(Reverse((cost + heuristic(next), next)));
+                }
+            }
+        }
+    }
+    None
+}
+
+/// Steps along the A* path towards the target, or straight at it when there
+/// is no path short enough to be worth following.
+pub fn move_astar(
+    id: usize,
+    target_x: i32,
+    target_y: i32,
+    game: &mut Game,
+    objects: &mut Vec<Object>,
+) {
+    let path = astar(
+        objects[id].pos(),
+        (target_x, target_y),
+        &game.map,
+        objects,
+        objects[id].swims,
+        MAX_PATH_LENGTH,
+    );
+    match path {
+        Some(path) if path[0] != (target_x, target_y) => {
+            let (x, y) = objects[id].pos();
+            move_by(id, path[0].0 - x, path[0].1 - y, game, objects);
+        }
+        _ => move_towards(id, target_x, target_y, game, objects),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tcod::colors::WHITE;
+
+    use super::*;
+    use crate::{
+        make_empty_map, new_game,
+        tile::{Tile, TileKind},
+        MAP_HEIGHT,
+    };
+
+    /// Surrounds the tile at (x, y) with walls.
+    fn wall_in(map: &mut Map, x: usize, y: usize) {
+        for wall_x in x - 1..=x + 1 {
+            for wall_y in y - 1..=y + 1 {
+                if (wall_x, wall_y) != (x, y) {
+                    map[wall_x][wall_y] = Tile::wall();
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn astar_walks_around_a_wall() {
+        let mut map = make_empty_map();
+        for y in 0..10 {
+            map[10][y] = Tile::wall();
+        }
+        let objects = vec![];
+        let path = astar((5, 5), (15, 5), &map, &objects, false, MAX_PATH_LENGTH).unwrap();
+
+        assert_eq!(path.last(), Some(&(15, 5)));
+        assert!(path
+            .iter()
+            .all(|&(x, y)| !map[x as usize][y as usize].blocked));
+        assert!(path.contains(&(10, 10)));
+        assert_eq!(path.len(), 10);
+    }
+
+    #[test]
+    fn astar_avoids_blocking_objects_but_reaches_an_occupied_goal() {
+        let map = make_empty_map();
+        let rock = Object::new(6, 5, 'O', WHITE, "boulder".to_string(), true, false);
+        let target = Object::new(7, 5, '@', WHITE, "me".to_string(), true, true);
+        let objects = vec![target, rock];
+        let path = astar((5, 5), (7, 5), &map, &objects, false, MAX_PATH_LENGTH).unwrap();
+
+        assert_eq!(path.len(), 2);
+        assert_ne!(path[0], (6, 5));
+        assert_eq!(path[1], (7, 5));
+    }
+
+    #[test]
+    fn astar_finds_nothing_when_walled_in_or_too_far() {
+        let mut map = make_empty_map();
+        for (x, y) in [
+            (4, 4),
+            (5, 4),
+            (6, 4),
+            (4, 5),
+            (6, 5),
+            (4, 6),
+            (5, 6),
+            (6, 6),
+        ] {
+            map[x][y] = Tile::wall();
+        }
+        let objects = vec![];
+        assert_eq!(
+            astar((5, 5), (20, 20), &map, &objects, false, MAX_PATH_LENGTH),
+            None
+        );
+
+        let open_map = make_empty_map();
+        assert_eq!(
+            astar((0, 0), (60, 0), &open_map, &objects, false, MAX_PATH_LENGTH),
+            None
+        );
+    }
+
+    #[test]
+    fn monsters_step_straight_at_the_target_when_there_is_no_path() {
+        let (mut game, mut objects) = new_game(5);
+        game.map = make_empty_map();
+        objects.truncate(1);
+        objects[0].set_pos(20, 5);
+        wall_in(&mut game.map, 20, 5);
+        let orc = Object::new(10, 5, 'o', WHITE, "orc".to_string(), true, true);
+        objects.push(orc);
+
+        move_astar(1, 20, 5, &mut game, &mut objects);
+        assert_eq!(objects[1].pos(), (11, 5));
+    }
+
+    #[test]
+    fn monsters_path_around_lava_and_only_swimmers_cross_deep_water() {
+        let mut map = make_empty_map();
+        for y in 0..12 {
+            map[10][y] = Tile::new(TileKind::Lava);
+        }
+        let objects = vec![];
+        let path = astar((5, 5), (15, 5), &map, &objects, false, MAX_PATH_LENGTH).unwrap();
+        assert_eq!(path.last(), Some(&(15, 5)));
+        assert!(path
+            .iter()
+            .all(|&(x, y)| map[x as usize][y as usize].kind != TileKind::Lava));
+
+        for y in 0..MAP_HEIGHT as usize {
+            map[10][y] = Tile::new(TileKind::DeepWater);
+        }
+        let path = astar((5, 5), (15, 5), &map, &objects, false, MAX_PATH_LENGTH);
+        assert_eq!(path, None);
+        let path = astar((5, 5), (15, 5), &map, &objects, true, MAX_PATH_LENGTH);
+        assert_eq!(path.map(|path| path.len()), Some(10));
+    }
+
+    #[test]
+    fn monsters_take_a_dry_detour_rather_than_wade() {
+        let mut map = make_empty_map();
+        for x in 4..=10 {
+            map[x][4] = Tile::wall();
+        }
+        for x in 6..=8 {
+            map[x][5] = Tile::new(TileKind::Water);
+        }
+        let objects = vec![];
+        // going round below the water is no more steps than wading through
+        let path = astar((5, 5), (9, 5), &map, &objects, false, MAX_PATH_LENGTH).unwrap();
+        assert_eq!(path.len(), 4);
+        assert!(path
+            .iter()
+            .all(|&(x, y)| map[x as usize][y as usize].kind == TileKind::Floor));
+    }
+}
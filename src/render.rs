@@ -0,0 +1,351 @@
+use std::fmt;
+
+use tcod::{
+    colors::{BLACK, WHITE},
+    console::{blit, Offscreen},
+    BackgroundFlag, Color, Console, TextAlignment,
+};
+
+use crate::{MAP_HEIGHT, MAP_WIDTH, PANEL_HEIGHT, SCREEN_HEIGHT, SCREEN_WIDTH};
+
+/// Something the game can draw on: a tcod console, or a plain grid of
+/// characters for running and testing without a window.
+pub trait Canvas {
+    fn width(&self) -> i32;
+    fn height(&self) -> i32;
+    fn clear(&mut self, background: Color);
+    fn put_char(&mut self, x: i32, y: i32, glyph: char, foreground: Color);
+    fn set_background(&mut self, x: i32, y: i32, background: Color);
+    fn print(&mut self, x: i32, y: i32, alignment: TextAlignment, foreground: Color, text: &str);
+    /// Prints `text` word-wrapped to `width` columns (and at most `height`
+    /// rows, unless `height` is 0).
+    fn print_rect(
+        &mut self,
+        x: i32,
+        y: i32,
+        width: i32,
+        height: i32,
+        foreground: Color,
+        text: &str,
+    );
+    /// The number of rows `print_rect` would use for `text`.
+    fn get_height_rect(&self, x: i32, y: i32, width: i32, height: i32, text: &str) -> i32;
+}
+
+/// A canvas that can be created off screen and composed onto another.
+pub trait Layer<Dest>: Canvas {
+    fn new_layer(width: i32, height: i32) -> Self;
+    fn blit(
+        &self,
+        source_pos: (i32, i32),
+        size: (i32, i32),
+        dest: &mut Dest,
+        dest_pos: (i32, i32),
+        foreground_alpha: f32,
+        background_alpha: f32,
+    );
+}
+
+/// The root console plus the map and status panel layers blitted onto it.
+pub struct Screen<R, L> {
+    pub root: R,
+    pub con: L,
+    pub panel: L,
+}
+
+impl Screen<CharGrid, CharGrid> {
+    /// A screen that lives only in memory, for running without a window.
+    pub fn in_memory() -> Self {
+        Screen {
+            root: CharGrid::new(SCREEN_WIDTH, SCREEN_HEIGHT),
+            con: CharGrid::new(MAP_WIDTH, MAP_HEIGHT),
+            panel: CharGrid::new(SCREEN_WIDTH, PANEL_HEIGHT),
+        }
+    }
+}
+
+impl<T: Console> Canvas for T {
+    fn width(&self) -> i32 {
+        Console::width(self)
+    }
+
+    fn height(&self) -> i32 {
+        Console::height(self)
+    }
+
+    fn clear(&mut self, background: Color) {
+        self.set_default_background(background);
+        Console::clear(self);
+    }
+
+    fn put_char(&mut self, x: i32, y: i32, glyph: char, foreground: Color) {
+        self.set_default_foreground(foreground);
+        Console::put_char(self, x, y, glyph, BackgroundFlag::None);
+    }
+
+    fn set_background(&mut self, x: i32, y: i32, background: Color) {
+        self.set_char_background(x, y, background, BackgroundFlag::Set);
+    }
+
+    fn print(&mut self, x: i32, y: i32, alignment: TextAlignment, foreground: Color, text: &str) {
+        self.set_default_foreground(foreground);
+        self.print_ex(x, y, BackgroundFlag::None, alignment, text);
+    }
+
+    fn print_rect(
+        &mut self,
+        x: i32,
+        y: i32,
+        width: i32,
+        height: i32,
+        foreground: Color,
+        text: &str,
+    ) {
+        self.set_default_foreground(foreground);
+        Console::print_rect(self, x, y, width, height, text);
+    }
+
+    fn get_height_rect(&self, x: i32, y: i32, width: i32, height: i32, text: &str) -> i32 {
+        Console::get_height_rect(self, x, y, width, height, text)
+    }
+}
+
+impl<D: Console> Layer<D> for Offscreen {
+    fn new_layer(width: i32, height: i32) -> Self {
+        Offscreen::new(width, height)
+    }
+
+    fn blit(
+        &self,
+        source_pos: (i32, i32),
+        size: (i32, i32),
+        dest: &mut D,
+        dest_pos: (i32, i32),
+        foreground_alpha: f32,
+        background_alpha: f32,
+    ) {
+        blit(
+            self,
+            source_pos,
+            size,
+            dest,
+            dest_pos,
+            foreground_alpha,
+            background_alpha,
+        );
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Cell {
+    pub glyph: char,
+    pub foreground: Color,
+    pub background: Color,
+}
+
+impl Cell {
+    fn blank(background: Color) -> Self {
+        Cell {
+            glyph: ' ',
+            foreground: WHITE,
+            background: background,
+        }
+    }
+}
+
+/// An in-memory console: every cell's glyph and colours, nothing else.
+#[derive(Clone, Debug, PartialEq)]
+pub struct CharGrid {
+    width: i32,
+    height: i32,
+    cells: Vec<Cell>,
+}
+
+impl CharGrid {
+    pub fn new(width: i32, height: i32) -> Self {
+        CharGrid {
+            width: width,
+            height: height,
+            cells: vec![Cell::blank(BLACK); (width * height) as usize],
+        }
+    }
+
+    pub fn cell(&self, x: i32, y: i32) -> Cell {
+        self.cells[self.index(x, y).expect("cell out of bounds")]
+    }
+
+    /// The glyphs of row `y`, without trailing blanks.
+    pub fn row(&self, y: i32) -> String {
+        let row: String = (0..self.width).map(|x| self.cell(x, y).glyph).collect();
+        row.trim_end().to_string()
+    }
+
+    fn index(&self, x: i32, y: i32) -> Option<usize> {
+        if x < 0 || y < 0 || x >= self.width || y >= self.height {
+            None
+        } else {
+            Some((y * self.width + x) as usize)
+        }
+    }
+
+    fn cell_mut(&mut self, x: i32, y: i32) -> Option<&mut Cell> {
+        self.index(x, y).map(move |index| &mut self.cells[index])
+    }
+}
+
+impl fmt::Display for CharGrid {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for y in 0..self.height {
+            writeln!(f, "{}", self.row(y))?;
+        }
+        Ok(())
+    }
+}
+
+/// Splits `text` into lines of at most `width` characters, breaking at
+/// spaces where possible and always at newlines.
+fn wrap(text: &str, width: i32) -> Vec<String> {
+    let width = width.max(1) as usize;
+    let mut lines = vec![];
+    for paragraph in text.split('\n') {
+        let mut line = String::new();
+        for word in paragraph.split(' ') {
+            let line_len = line.chars().count();
+            let word_len = word.chars().count();
+            if line_len > 0 && line_len + 1 + word_len > width {
+                lines.push(line);
+                line = String::new();
+            }
+            if !line.is_empty() {
+                line.push(' ');
+            }
+            line.push_str(word);
+            while line.chars().count() > width {
+                let rest = line.chars().skip(width).collect();
+                lines.push(line.chars().take(width).collect());
+                line = rest;
+            }
+        }
+        lines.push(line);
+    }
+    // a trailing newline does not start another printed line
+    if text.ends_with('\n') {
+        lines.pop();
+    }
+    lines
+}
+
+impl Canvas for CharGrid {
+    fn width(&self) -> i32 {
+        self.width
+    }
+
+    fn height(&self) -> i32 {
+        self.height
+    }
+
+    fn clear(&mut self, background: Color) {
+        for cell in self.cells.iter_mut() {
+            *cell = Cell::blank(background);
+        }
+    }
+
+    fn put_char(&mut self, x: i32, y: i32, glyph: char, foreground: Color) {
+        if let Some(cell) = self.cell_mut(x, y) {
+            cell.glyph = glyph;
+            cell.foreground = foreground;
+        }
+    }
+
+    fn set_background(&mut self, x: i32, y: i32, background: Color) {
+        if let Some(cell) = self.cell_mut(x, y) {
+            cell.background = background;
+        }
+    }
+
+    fn print(&mut self, x: i32, y: i32, alignment: TextAlignment, foreground: Color, text: &str) {
+        let len = text.chars().count() as i32;
+        let start = match alignment {
+            TextAlignment::Left => x,
+            TextAlignment::Center => x - len / 2,
+            TextAlignment::Right => x - len + 1,
+        };
+        for (i, glyph) in text.chars().enumerate() {
+            self.put_char(start + i as i32, y, glyph, foreground);
+        }
+    }
+
+    fn print_rect(
+        &mut self,
+        x: i32,
+        y: i32,
+        width: i32,
+        height: i32,
+        foreground: Color,
+        text: &str,
+    ) {
+        for (row, line) in wrap(text, width).iter().enumerate() {
+            if height > 0 && row as i32 >= height {
+                break;
+            }
+            self.print(x, y + row as i32, TextAlignment::Left, foreground, line);
+        }
+    }
+
+    fn get_height_rect(&self, _x: i32, _y: i32, width: i32, height: i32, text: &str) -> i32 {
+        let lines = wrap(text, width).len() as i32;
+        if height > 0 {
+            lines.min(height)
+        } else {
+            lines
+        }
+    }
+}
+
+/// The colour `coefficient` of the way from `from` to `to`.
+pub fn lerp(from: Color, to: Color, coefficient: f32) -> Color {
+    let mix = |a: u8, b: u8| (a as f32 + (b as f32 - a as f32) * coefficient).round() as u8;
+    Color {
+        r: mix(from.r, to.r),
+        g: mix(from.g, to.g),
+        b: mix(from.b, to.b),
+    }
+}
+
+impl Layer<CharGrid> for CharGrid {
+    fn new_layer(width: i32, height: i32) -> Self {
+        CharGrid::new(width, height)
+    }
+
+    fn blit(
+        &self,
+        source_pos: (i32, i32),
+        size: (i32, i32),
+        dest: &mut CharGrid,
+        dest_pos: (i32, i32),
+        foreground_alpha: f32,
+        background_alpha: f32,
+    ) {
+        for dy in 0..size.1 {
+            for dx in 0..size.0 {
+                let source = match self.index(source_pos.0 + dx, source_pos.1 + dy) {
+                    Some(index) => self.cells[index],
+                    None => continue,
+                };
+                if let Some(cell) = dest.cell_mut(dest_pos.0 + dx, dest_pos.1 + dy) {
+                    cell.background = lerp(cell.background, source.background, background_alpha);
+                    if source.glyph != ' ' {
+                        cell.glyph = source.glyph;
+                        cell.foreground =
+                            lerp(cell.foreground, source.foreground, foreground_alpha);
+                    } else {
+                        // blank cells let the glyph below show through,
+                        // tinted like the background
+                        cell.foreground =
+                            lerp(cell.foreground, source.background, background_alpha);
+                    }
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,407 @@
+use std::{
+    collections::VecDeque,
+    error::Error,
+    fs::{self, File, OpenOptions},
+    io::{self, Write},
+    time::{Duration, Instant},
+};
+
+use serde::{Deserialize, Serialize};
+use tcod::{colors::LIGHT_YELLOW, input::KeyCode, TextAlignment};
+
+use crate::{
+    can_level_up, choose_stat, command::Command, components::Stat, frontend::Frontend, game::Game,
+    handle_keys, object::Object, render::Canvas, PlayerInput, Tcod,
+};
+
+pub const REPLAY_FILE: &str = "replay";
+/// Bump this whenever the entries change shape, so that older replays are
+/// turned away rather than misread.
+pub const REPLAY_VERSION: u32 = 1;
+/// The pause between commands at each playback speed, fastest first.
+const STEP_DELAYS_MS: [u64; 6] = [0, 30, 100, 250, 500, 1000];
+const DEFAULT_SPEED: usize = 2;
+
+/// One line of a replay file.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum Entry {
+    /// The first line: the game that was started from `seed`.
+    Start {
+        version: u32,
+        seed: u64,
+    },
+    Command(Command),
+    /// The state the game was in at this point, written whenever play stops.
+    StateHash(u64),
+}
+
+/// A hash of everything that decides how the game goes on. The message log
+/// is left out, as the interface adds prompts of its own to it.
+pub fn state_hash(game: &Game, objects: &[Object]) -> u64 {
+    // through `Value`, whose maps are sorted, so the levels hash the same
+    // whatever order they are stored in
+    let state = serde_json::to_value((
+        &game.map,
+        &game.inventory,
+        &game.rng,
+        game.dungeon_level,
+        &game.levels,
+        objects,
+    ))
+    .expect("the game state can be serialized");
+    // FNV-1a, which unlike `DefaultHasher` is the same in every build
+    state
+        .to_string()
+        .bytes()
+        .fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+            (hash ^ byte as u64).wrapping_mul(0x0000_0100_0000_01b3)
+        })
+}
+
+/// Writes a replay as the game is played, a line at a time so that it
+/// survives a crash.
+pub struct Recorder<W = File> {
+    out: W,
+}
+
+impl<W: Write> Recorder<W> {
+    /// Starts a replay of the game started from `seed`.
+    pub fn new(out: W, seed: u64) -> io::Result<Self> {
+        let mut recorder = Recorder { out: out };
+        recorder.write(&Entry::Start {
+            version: REPLAY_VERSION,
+            seed: seed,
+        })?;
+        Ok(recorder)
+    }
+
+    pub fn write(&mut self, entry: &Entry) -> io::Result<()> {
+        writeln!(self.out, "{}", serde_json::to_string(entry)?)?;
+        self.out.flush()
+    }
+}
+
+impl Recorder {
+    /// Starts a new replay file, replacing any earlier one.
+    pub fn start(path: &str, seed: u64) -> io::Result<Self> {
+        Recorder::new(File::create(path)?, seed)
+    }
+
+    /// Carries on with the replay file of a saved game, as long as the file
+    /// is that game's and not one started since.
+    pub fn resume(path: &str, seed: u64) -> Result<Self, Box<dyn Error>> {
+        let recorded = load_replay(path)?.seed;
+        if recorded != seed {
+            return Err(format!(
+                "{} belongs to the game with seed {}, not {}",
+                path, recorded, seed
+            )
+            .into());
+        }
+        Ok(Recorder {
+            out: OpenOptions::new().append(true).open(path)?,
+        })
+    }
+}
+
+/// A recorded game: the seed it started from and everything that happened
+/// since.
+#[derive(Debug, PartialEq)]
+pub struct Replay {
+    pub seed: u64,
+    pub entries: Vec<Entry>,
+}
+
+pub fn load_replay(path: &str) -> Result<Replay, Box<dyn Error>> {
+    parse_replay(&fs::read_to_string(path)?)
+}
+
+pub fn parse_replay(text: &str) -> Result<Replay, Box<dyn Error>> {
+    let mut entries = vec![];
+    for (number, line) in text.lines().enumerate() {
+        if line.trim().is_empty() {
+            continue;
+        }
+        let entry: Entry =
+            serde_json::from_str(line).map_err(|e| format!("line {}: {}", number + 1, e))?;
+        entries.push(entry);
+    }
+    match entries.first() {
+        Some(&Entry::Start { version, seed }) if version == REPLAY_VERSION => Ok(Replay {
+            seed: seed,
+            entries: entries.split_off(1),
+        }),
+        Some(&Entry::Start { version, .. }) => Err(format!(
+            "replay version {} is not supported (expected {})",
+            version, REPLAY_VERSION
+        )
+        .into()),
+        _ => Err("the replay does not start with the game's seed".into()),
+    }
+}
+
+/// Where the player's commands come from while playing.
+pub trait Controller {
+    /// What the player does next. Asked once a frame.
+    fn next_input<F: Frontend>(
+        &mut self,
+        tcod: &mut Tcod<F>,
+        game: &mut Game,
+        objects: &mut Vec<Object>,
+    ) -> PlayerInput;
+
+    /// The stat to raise, if the player can level up.
+    fn level_up_choice<F: Frontend>(
+        &mut self,
+        tcod: &mut Tcod<F>,
+        objects: &[Object],
+    ) -> Option<Stat>;
+
+    /// Called with each command just before it is carried out.
+    fn record(&mut self, _command: Command) {}
+
+    /// Draws anything the controller shows over the game.
+    fn draw_status<C: Canvas>(&self, _root: &mut C) {}
+}
+
+/// Plays from the keyboard, recording every command if there is a recorder.
+pub struct Keyboard<W = File> {
+    pub recorder: Option<Recorder<W>>,
+}
+
+impl<W> Default for Keyboard<W> {
+    fn default() -> Self {
+        Keyboard { recorder: None }
+    }
+}
+
+impl Keyboard {
+    pub fn start_recording(&mut self, path: &str, seed: u64) {
+        self.recorder = Recorder::start(path, seed)
+            .map_err(|e| eprintln!("Could not record a replay: {}", e))
+            .ok();
+    }
+
+    /// Records into the replay file of the saved game started from `seed`.
+    pub fn resume_recording(&mut self, path: &str, seed: u64) {
+        self.recorder = Recorder::resume(path, seed)
+            .map_err(|e| eprintln!("Could not record a replay: {}", e))
+            .ok();
+    }
+}
+
+impl<W: Write> Keyboard<W> {
+    fn write(&mut self, entry: &Entry) {
+        if let Some(recorder) = self.recorder.as_mut() {
+            if let Err(e) = recorder.write(entry) {
+                // carry on playing, just without a replay
+                eprintln!("Could not record the replay: {}", e);
+                self.recorder = None;
+            }
+        }
+    }
+
+    /// Notes the state the game is in, for a replay to be checked against.
+    pub fn record_state(&mut self, game: &Game, objects: &[Object]) {
+        self.write(&Entry::StateHash(state_hash(game, objects)));
+    }
+}
+
+impl<W: Write> Controller for Keyboard<W> {
+    fn next_input<F: Frontend>(
+        &mut self,
+        tcod: &mut Tcod<F>,
+        game: &mut Game,
+        objects: &mut Vec<Object>,
+    ) -> PlayerInput {
+        handle_keys(tcod, 0, objects, game)
+    }
+
+    fn level_up_choice<F: Frontend>(
+        &mut self,
+        tcod: &mut Tcod<F>,
+        objects: &[Object],
+    ) -> Option<Stat> {
+        choose_stat(tcod, objects)
+    }
+
+    fn record(&mut self, command: Command) {
+        self.write(&Entry::Command(command));
+    }
+}
+
+/// Plays a replay back, checking the game against the state hashes in it.
+/// Space pauses, '.' steps while paused, '+' and '-' change the speed and
+/// Escape stops.
+pub struct Playback {
+    entries: VecDeque<Entry>,
+    /// How many commands have been played out of how many there are.
+    played: usize,
+    total: usize,
+    /// An index into `STEP_DELAYS_MS`.
+    pub speed: usize,
+    pub paused: bool,
+    last_step: Instant,
+    /// How many state hashes matched and how many didn't.
+    pub matched: usize,
+    pub mismatched: usize,
+}
+
+impl Playback {
+    pub fn new(replay: Replay) -> Self {
+        let total = replay
+            .entries
+            .iter()
+            .filter(|entry| matches!(entry, Entry::Command(_)))
+            .count();
+        Playback {
+            entries: replay.entries.into(),
+            played: 0,
+            total: total,
+            speed: DEFAULT_SPEED,
+            paused: false,
+            last_step: Instant::now(),
+            matched: 0,
+            mismatched: 0,
+        }
+    }
+
+    /// Checks the game against the hashes up to the next command.
+    fn check_hashes(&mut self, game: &Game, objects: &[Object]) {
+        while let Some(&Entry::StateHash(hash)) = self.entries.front() {
+            self.entries.pop_front();
+            if state_hash(game, objects) == hash {
+                self.matched += 1;
+            } else {
+                self.mismatched += 1;
+                eprintln!(
+                    "The game differs from the recording after {} commands.",
+                    self.played
+                );
+            }
+        }
+    }
+
+    fn next_command(&mut self, game: &Game, objects: &[Object]) -> Option<Command> {
+        self.check_hashes(game, objects);
+        while let Some(entry) = self.entries.pop_front() {
+            if let Entry::Command(command) = entry {
+                self.played += 1;
+                return Some(command);
+            }
+        }
+        None
+    }
+
+    /// Checks the hashes left once play has stopped, and sums up how the
+    /// replay went.
+    pub fn finish(&mut self, game: &Game, objects: &[Object]) -> String {
+        self.check_hashes(game, objects);
+        let played = format!("Played {} of {} commands.", self.played, self.total);
+        if self.mismatched > 0 {
+            format!(
+                "{} The game did not match the recording: {} of {} state checks failed.",
+                played,
+                self.mismatched,
+                self.mismatched + self.matched
+            )
+        } else if self.matched > 0 {
+            format!(
+                "{} The game matched the recording at all {} state checks.",
+                played, self.matched
+            )
+        } else {
+            format!("{} There was no recorded state to check against.", played)
+        }
+    }
+}
+
+impl Controller for Playback {
+    fn next_input<F: Frontend>(
+        &mut self,
+        tcod: &mut Tcod<F>,
+        game: &mut Game,
+        objects: &mut Vec<Object>,
+    ) -> PlayerInput {
+        let key = tcod.key;
+        let mut step = false;
+        match (key.code, key.printable) {
+            (KeyCode::Escape, _) => return PlayerInput::Exit,
+            (KeyCode::Text, ' ') | (KeyCode::Spacebar, _) => self.paused = !self.paused,
+            (KeyCode::Text, '.') => step = self.paused,
+            (KeyCode::Text, '+') => self.speed = self.speed.saturating_sub(1),
+            (KeyCode::Text, '-') => self.speed = (self.speed + 1).min(STEP_DELAYS_MS.len() - 1),
+            _ => {}
+        }
+        let delay = Duration::from_millis(STEP_DELAYS_MS[self.speed]);
+        if !step && (self.paused || self.last_step.elapsed() < delay) {
+            return PlayerInput::Nothing;
+        }
+        self.last_step = Instant::now();
+        match self.next_command(game, objects) {
+            Some(command) => PlayerInput::Command(command),
+            None => {
+                // the end of the recording; stay on it until Escape
+                self.paused = true;
+                PlayerInput::Nothing
+            }
+        }
+    }
+
+    fn level_up_choice<F: Frontend>(
+        &mut self,
+        _tcod: &mut Tcod<F>,
+        objects: &[Object],
+    ) -> Option<Stat> {
+        // the recorded choice comes right after the command that earned it
+        if !can_level_up(&objects[0]) {
+            return None;
+        }
+        match self.entries.front() {
+            Some(&Entry::Command(Command::LevelUp(stat))) => {
+                self.entries.pop_front();
+                self.played += 1;
+                Some(stat)
+            }
+            _ => None,
+        }
+    }
+
+    fn draw_status<C: Canvas>(&self, root: &mut C) {
+        let state = if self.entries.is_empty() {
+            "finished".to_string()
+        } else if self.paused {
+            "paused".to_string()
+        } else {
+            format!("{} ms a step", STEP_DELAYS_MS[self.speed])
+        };
+        let status = format!(
+            "Replay {}/{} ({}): Space pauses, . steps, +/- speed, Esc quits",
+            self.played, self.total, state
+        );
+        root.print(0, 0, TextAlignment::Left, LIGHT_YELLOW, &status);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn replays_read_back_as_recorded_and_only_in_this_version() {
+        let mut recorder = Recorder::new(vec![], 7).unwrap();
+        recorder.write(&Entry::Command(Command::Wait)).unwrap();
+        recorder.write(&Entry::StateHash(42)).unwrap();
+        let text = String::from_utf8(recorder.out).unwrap();
+        assert_eq!(
+            parse_replay(&text).unwrap(),
+            Replay {
+                seed: 7,
+                entries: vec![Entry::Command(Command::Wait), Entry::StateHash(42)],
+            }
+        );
+
+        assert!(parse_replay("{\"Start\":{\"version\":0,\"seed\":1}}").is_err());
+        assert!(parse_replay("\"Wait\"").is_err());
+    }
+}
@@ -0,0 +1,45 @@
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+/// The game's single source of randomness. Every map, spawn and combat roll
+/// draws from this generator so that a seed reproduces a run exactly.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct GameRng {
+    seed: u64,
+    state: [u32; 4],
+}
+
+impl GameRng {
+    pub fn new(seed: u64) -> Self {
+        // spread the seed over the xorshift state with splitmix64, which
+        // also guarantees the state is never all zeroes
+        let mut mix = seed;
+        let mut next = || {
+            mix = mix.wrapping_add(0x9E37_79B9_7F4A_7C15);
+            let mut z = mix;
+            z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+            z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+            z ^ (z >> 31)
+        };
+        let (a, b) = (next(), next());
+        GameRng {
+            seed: seed,
+            state: [a as u32, (a >> 32) as u32, b as u32, (b >> 32) as u32 | 1],
+        }
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+}
+
+impl Rng for GameRng {
+    // xorshift128
+    fn next_u32(&mut self) -> u32 {
+        let [x, y, z, w] = self.state;
+        let t = x ^ (x << 11);
+        let new_w = w ^ (w >> 19) ^ t ^ (t >> 8);
+        self.state = [y, z, w, new_w];
+        new_w
+    }
+}
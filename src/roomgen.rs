@@ -1,4 +1,6 @@
-#[derive(Clone)]
+use rand::Rng;
+
+#[derive(Clone, Debug)]
 pub struct Rect {
     pub x1: i32,
     pub y1: i32,
@@ -22,6 +24,13 @@ impl Rect {
         (center_x, center_y)
     }
 
+    /// A random tile inside the room's walls.
+    pub fn random_tile<R: Rng>(&self, rng: &mut R) -> (i32, i32) {
+        let x = rng.gen_range(self.x1 + 1, self.x2);
+        let y = rng.gen_range(self.y1 + 1, self.y2);
+        (x, y)
+    }
+
     pub fn intersects_with(&self, other: &Rect) -> bool {
         (self.x1 <= other.x2)
             && (self.x2 >= other.x1)
@@ -0,0 +1,103 @@
+use std::{error::Error, fs, io::ErrorKind, path::Path};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{game::Game, object::Object};
+
+pub const SAVE_FILE: &str = "savegame";
+
+/// Bump this whenever a saved type changes shape; older files are rejected
+/// instead of being misread.
+pub const SAVE_VERSION: u32 = 10;
+
+#[derive(Serialize)]
+struct SaveData<'a> {
+    version: u32,
+    game: &'a Game,
+    objects: &'a [Object],
+}
+
+#[derive(Deserialize)]
+struct SaveVersion {
+    version: u32,
+}
+
+#[derive(Deserialize)]
+struct LoadData {
+    game: Game,
+    objects: Vec<Object>,
+}
+
+pub fn to_string(game: &Game, objects: &[Object]) -> Result<String, Box<dyn Error>> {
+    let data = SaveData {
+        version: SAVE_VERSION,
+        game: game,
+        objects: objects,
+    };
+    Ok(serde_json::to_string(&data)?)
+}
+
+pub fn from_str(save: &str) -> Result<(Game, Vec<Object>), Box<dyn Error>> {
+    let SaveVersion { version } = serde_json::from_str(save)?;
+    if version != SAVE_VERSION {
+        return Err(format!(
+            "Save file version {} is not supported (expected {}).",
+            version, SAVE_VERSION
+        )
+        .into());
+    }
+    let LoadData { game, objects } = serde_json::from_str(save)?;
+    Ok((game, objects))
+}
+
+pub fn save_game(game: &Game, objects: &[Object]) -> Result<(), Box<dyn Error>> {
+    fs::write(SAVE_FILE, to_string(game, objects)?)?;
+    Ok(())
+}
+
+pub fn load_game() -> Result<(Game, Vec<Object>), Box<dyn Error>> {
+    from_str(&fs::read_to_string(SAVE_FILE)?)
+}
+
+pub fn save_exists() -> bool {
+    Path::new(SAVE_FILE).exists()
+}
+
+/// Removes the save, e.g. once its player has died.
+pub fn delete_save() -> Result<(), Box<dyn Error>> {
+    match fs::remove_file(SAVE_FILE) {
+        Err(e) if e.kind() != ErrorKind::NotFound => Err(e.into()),
+        _ => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::Rng;
+
+    use super::*;
+    use crate::{dungeon, new_game};
+
+    #[test]
+    fn saved_game_loads_back_unchanged() {
+        let (mut game, mut objects) = new_game(2024);
+        game.map[3][4].explored = true;
+        game.inventory.push(objects.pop().unwrap());
+        game.rng.gen::<u32>();
+        dungeon::enter_level(&mut game, &mut objects, 2);
+
+        let saved = to_string(&game, &objects).unwrap();
+        let (loaded_game, loaded_objects) = from_str(&saved).unwrap();
+
+        assert_eq!(loaded_game, game);
+        assert_eq!(loaded_objects, objects);
+    }
+
+    #[test]
+    fn save_from_another_version_is_rejected() {
+        let (game, objects) = new_game(1);
+        let saved = to_string(&game, &objects).unwrap();
+        let saved = saved.replacen(&format!("\"version\":{}", SAVE_VERSION), "\"version\":0", 1);
+        assert!(from_str(&saved).is_err());
+    }
+}
@@ -0,0 +1,121 @@
+use crate::{game::Game, object::Object, ACTION_COST};
+
+/// Advances time until the player has gathered enough energy to act again.
+/// Every tick each fighter gains energy equal to its speed, and every AI
+/// with at least `ACTION_COST` energy takes turns through `act` until it
+/// runs short. Fast monsters may act several times per player turn and
+/// slow ones only every few turns.
+pub fn advance<F>(game: &mut Game, objects: &mut Vec<Object>, mut act: F)
+where
+    F: FnMut(usize, &mut Game, &mut Vec<Object>),
+{
+    let player_can_act = |objects: &Vec<Object>| {
+        objects[0].is_alive && objects[0].fighter.map_or(false, |f| f.speed > 0)
+    };
+    while player_can_act(objects) && objects[0].energy < ACTION_COST {
+        tick(game, objects, &mut act);
+    }
+}
+
+fn tick<F>(game: &mut Game, objects: &mut Vec<Object>, act: &mut F)
+where
+    F: FnMut(usize, &mut Game, &mut Vec<Object>),
+{
+    for id in 0..objects.len() {
+        if let Some(fighter) = objects[id].fighter {
+            objects[id].energy += fighter.speed;
+        }
+        if id == 0 {
+            continue;
+        }
+        while objects[id].ai.is_some() && objects[id].energy >= ACTION_COST && objects[0].is_alive {
+            act(id, game, objects);
+            objects[id].energy -= ACTION_COST;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{new_game, templates, test::fighter_with_speed, NORMAL_SPEED, PICK_UP_COST};
+
+    /// Has the player spend `costs` one after the other and counts how often
+    /// each other object got to act.
+    fn count_actions(costs: &[i32]) -> Vec<usize> {
+        let (mut game, _) = new_game(3);
+        let mut player = fighter_with_speed(NORMAL_SPEED);
+        player.ai = None;
+        player.energy = ACTION_COST;
+        let mut objects = vec![
+            player,
+            fighter_with_speed(2 * NORMAL_SPEED),
+            fighter_with_speed(NORMAL_SPEED),
+            fighter_with_speed(NORMAL_SPEED / 2),
+        ];
+        let mut actions = vec![0; objects.len()];
+        for &cost in costs {
+            objects[0].energy -= cost;
+            advance(&mut game, &mut objects, |id, _, _| actions[id] += 1);
+        }
+        actions
+    }
+
+    #[test]
+    fn fast_monsters_act_more_often_than_slow_ones() {
+        let actions = count_actions(&[ACTION_COST; 4]);
+        assert_eq!(actions, vec![0, 8, 4, 2]);
+
+        // bats and zombies are the fast and slow monsters met in the dungeon
+        templates::load().unwrap();
+        let speed = |name: &str| {
+            let monsters = &templates::templates().monsters;
+            monsters
+                .iter()
+                .find(|m| m.name == name)
+                .unwrap()
+                .fighter
+                .speed
+        };
+        assert_eq!(speed("Bat"), 2 * NORMAL_SPEED);
+        assert_eq!(speed("Zombie"), NORMAL_SPEED / 2);
+    }
+
+    #[test]
+    fn cheap_actions_give_monsters_less_time() {
+        let actions = count_actions(&[PICK_UP_COST, PICK_UP_COST]);
+        assert_eq!(actions, vec![0, 2, 1, 0]);
+    }
+
+    #[test]
+    fn player_that_has_not_acted_gets_no_monster_turns() {
+        let actions = count_actions(&[0, 0]);
+        assert_eq!(actions, vec![0, 0, 0, 0]);
+    }
+
+    #[test]
+    fn bats_act_twice_a_turn_and_zombies_every_other_turn() {
+        templates::load().unwrap();
+        let spawn = |name: &str| {
+            let monsters = &templates::templates().monsters;
+            monsters
+                .iter()
+                .find(|m| m.name == name)
+                .unwrap()
+                .spawn(0, 0)
+        };
+        let (mut game, mut objects) = new_game(3);
+        objects.truncate(1);
+        objects[0].energy = ACTION_COST;
+        objects.push(spawn("Bat"));
+        objects.push(spawn("Zombie"));
+
+        let mut actions = vec![0; objects.len()];
+        for turn in 1..=4 {
+            objects[0].energy -= ACTION_COST;
+            advance(&mut game, &mut objects, |id, _, _| actions[id] += 1);
+            assert_eq!(actions[1], 2 * turn);
+        }
+        assert_eq!(actions[2], 2);
+    }
+}
@@ -1,7 +1,9 @@
-use tcod::{colors::WHITE, console::Offscreen, BackgroundFlag, Color, Console, TextAlignment};
+use tcod::{colors::WHITE, Color, TextAlignment};
+
+use crate::render::Canvas;
 
 pub fn render_bar(
-    panel: &mut Offscreen,
+    panel: &mut dyn Canvas,
     x: i32,
     y: i32,
     total_width: i32,
@@ -12,18 +14,19 @@ pub fn render_bar(
     back_colour: Color,
 ) {
     let bar_width = (value as f32 / maximum as f32 * total_width as f32) as i32;
-    panel.set_default_background(back_colour);
-    panel.rect(x, y, total_width, 1, false, BackgroundFlag::Screen);
-    panel.set_default_background(back_colour);
-    if bar_width > 0 {
-        panel.rect(x, y, bar_width, 1, false, BackgroundFlag::Screen);
+    for bar_x in x..x + total_width {
+        let colour = if bar_x < x + bar_width {
+            bar_colour
+        } else {
+            back_colour
+        };
+        panel.set_background(bar_x, y, colour);
     }
-    panel.set_default_foreground(WHITE);
-    panel.print_ex(
+    panel.print(
         x + total_width / 2,
         y,
-        BackgroundFlag::None,
         TextAlignment::Center,
+        WHITE,
         &format!("{}: {}/{}", name, value, maximum),
     );
 }
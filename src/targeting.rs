@@ -0,0 +1,218 @@
+use tcod::{
+    colors::{BLACK, WHITE},
+    input::{KeyCode, Mouse},
+    map::Map as FovMap,
+};
+
+use crate::{
+    frontend::{Frontend, Input},
+    game::Game,
+    is_blocked, keys,
+    object::Object,
+    render::Canvas,
+    render_all, Map, Tcod, COLOR_BLOCKED_LINE, COLOR_LINE_OF_FIRE, COLOR_TARGET_RANGE, MAP_HEIGHT,
+    MAP_WIDTH,
+};
+
+/// Finds the closest monster the player can see, up to `max_range` away.
+pub fn closest_monster(fov: &FovMap, objects: &[Object], max_range: f32) -> Option<usize> {
+    visible_monsters(fov, objects)
+        .into_iter()
+        .find(|&id| objects[0].distance_to(&objects[id]) <= max_range)
+}
+
+/// The monsters the player can see, closest first.
+fn visible_monsters(fov: &FovMap, objects: &[Object]) -> Vec<usize> {
+    let mut monsters: Vec<usize> = (1..objects.len())
+        .filter(|&id| objects[id].fighter.is_some() && fov.is_in_fov(objects[id].x, objects[id].y))
+        .collect();
+    monsters.sort_by(|&a, &b| {
+        let distance_a = objects[0].distance_to(&objects[a]);
+        let distance_b = objects[0].distance_to(&objects[b]);
+        distance_a.partial_cmp(&distance_b).unwrap()
+    });
+    monsters
+}
+
+/// The tiles on a straight line from `from` to `to`, without `from` itself.
+pub fn line(from: (i32, i32), to: (i32, i32)) -> Vec<(i32, i32)> {
+    let (mut x, mut y) = from;
+    let dx = (to.0 - x).abs();
+    let dy = -(to.1 - y).abs();
+    let step_x = if x < to.0 { 1 } else { -1 };
+    let step_y = if y < to.1 { 1 } else { -1 };
+    let mut error = dx + dy;
+    let mut tiles = vec![];
+    while (x, y) != to {
+        let doubled = 2 * error;
+        if doubled >= dy {
+            error += dy;
+            x += step_x;
+        }
+        if doubled <= dx {
+            error += dx;
+            y += step_y;
+        }
+        tiles.push((x, y));
+    }
+    tiles
+}
+
+/// The line from the player to `target`, and whether anything stands in the
+/// way before it gets there.
+pub fn line_of_fire(target: (i32, i32), map: &Map, objects: &[Object]) -> (Vec<(i32, i32)>, bool) {
+    let tiles = line(objects[0].pos(), target);
+    let clear = tiles
+        .iter()
+        .take(tiles.len().saturating_sub(1))
+        .all(|&(x, y)| !is_blocked(x, y, map, objects));
+    (tiles, clear)
+}
+
+/// Lets the player pick a visible tile, up to `max_range` away and with a
+/// clear line of fire to it. Returns `None` if they cancel.
+pub fn target_tile<F: Frontend>(
+    tcod: &mut Tcod<F>,
+    game: &mut Game,
+    objects: &[Object],
+    max_range: Option<f32>,
+) -> Option<(i32, i32)> {
+    select_tile(tcod, game, objects, max_range, true)
+}
+
+/// Like `target_tile`, but only accepts a tile with a monster on it.
+pub fn target_monster<F: Frontend>(
+    tcod: &mut Tcod<F>,
+    game: &mut Game,
+    objects: &[Object],
+    max_range: Option<f32>,
+) -> Option<usize> {
+    loop {
+        let (x, y) = target_tile(tcod, game, objects, max_range)?;
+        let monster = (1..objects.len())
+            .find(|&id| objects[id].pos() == (x, y) && objects[id].fighter.is_some());
+        if monster.is_some() {
+            return monster;
+        }
+    }
+}
+
+/// Lets the player pick any visible tile, to see what is there.
+pub fn look<F: Frontend>(
+    tcod: &mut Tcod<F>,
+    game: &mut Game,
+    objects: &[Object],
+) -> Option<(i32, i32)> {
+    select_tile(tcod, game, objects, None, false)
+}
+
+/// Targeting mode. A cursor starts on the closest visible monster (or the
+/// player) and follows the movement keys of every bound profile and the
+/// mouse; Tab cycles through the visible monsters. Enter or a left-click
+/// picks the tile under the cursor, Escape or a right-click cancels.
+fn select_tile<F: Frontend>(
+    tcod: &mut Tcod<F>,
+    game: &mut Game,
+    objects: &[Object],
+    max_range: Option<f32>,
+    needs_line_of_fire: bool,
+) -> Option<(i32, i32)> {
+    let monsters = visible_monsters(&tcod.fov, objects);
+    let mut cycle = 0;
+    let mut cursor = monsters
+        .first()
+        .map_or(objects[0].pos(), |&id| objects[id].pos());
+    let mut last_mouse = (tcod.mouse.cx, tcod.mouse.cy);
+
+    while !tcod.frontend.window_closed() {
+        let (key, mouse) = match tcod.frontend.check_for_event() {
+            Some(Input::Key(k)) => (k, None),
+            Some(Input::Mouse(m)) => (Default::default(), Some(m)),
+            None => (Default::default(), None),
+        };
+        if let Some(mouse) = mouse {
+            tcod.mouse = mouse;
+            // a click picks the tile under the pointer, even if it never moved
+            if (mouse.cx, mouse.cy) != last_mouse || mouse.lbutton_pressed {
+                last_mouse = (mouse.cx, mouse.cy);
+                cursor = (mouse.cx as i32, mouse.cy as i32);
+            }
+        }
+        let (dx, dy) = keys::bindings()
+            .action_for(key)
+            .and_then(|action| action.direction())
+            .unwrap_or((0, 0));
+        cursor = (
+            (cursor.0 + dx).clamp(0, MAP_WIDTH - 1),
+            (cursor.1 + dy).clamp(0, MAP_HEIGHT - 1),
+        );
+        if key.code == KeyCode::Tab && !monsters.is_empty() {
+            cycle = (cycle + 1) % monsters.len();
+            cursor = objects[monsters[cycle]].pos();
+        }
+
+        let (line, clear) = line_of_fire(cursor, &game.map, objects);
+        let in_range = max_range.map_or(true, |range| {
+            objects[0].distance(cursor.0, cursor.1) <= range
+        });
+        let valid = tcod.fov.is_in_fov(cursor.0, cursor.1)
+            && in_range
+            && (!needs_line_of_fire
+                || (clear && !game.map[cursor.0 as usize][cursor.1 as usize].blocked));
+
+        let confirmed = matches!(key.code, KeyCode::Enter | KeyCode::NumPadEnter)
+            || mouse.map_or(false, |m| m.lbutton_pressed);
+        if confirmed && valid {
+            return Some(cursor);
+        }
+        if key.code == KeyCode::Escape || mouse.map_or(false, |m| m.rbutton_pressed) {
+            return None;
+        }
+
+        // the map, with the names of whatever is under the cursor
+        tcod.frontend.screen().con.clear(BLACK);
+        let look_at = Mouse {
+            cx: cursor.0 as isize,
+            cy: cursor.1 as isize,
+            ..tcod.mouse
+        };
+        render_all(
+            tcod.frontend.screen(),
+            &mut tcod.fov,
+            look_at,
+            game,
+            objects,
+            false,
+        );
+
+        // the map is blitted at the root's origin, so map and root
+        // coordinates are the same
+        let root = &mut tcod.frontend.screen().root;
+        if let Some(range) = max_range {
+            for y in 0..MAP_HEIGHT {
+                for x in 0..MAP_WIDTH {
+                    let floor = !game.map[x as usize][y as usize].blocked;
+                    if floor && tcod.fov.is_in_fov(x, y) && objects[0].distance(x, y) <= range {
+                        root.set_background(x, y, COLOR_TARGET_RANGE);
+                    }
+                }
+            }
+        }
+        if needs_line_of_fire {
+            let mut blocked = false;
+            for &(x, y) in &line {
+                let colour = if blocked {
+                    COLOR_BLOCKED_LINE
+                } else {
+                    COLOR_LINE_OF_FIRE
+                };
+                root.set_background(x, y, colour);
+                blocked = blocked || is_blocked(x, y, &game.map, objects);
+            }
+        }
+        let cursor_colour = if valid { WHITE } else { COLOR_BLOCKED_LINE };
+        root.set_background(cursor.0, cursor.1, cursor_colour);
+        tcod.frontend.flush();
+    }
+    None
+}
@@ -0,0 +1,376 @@
+use std::{collections::HashSet, error::Error, fs, sync::OnceLock};
+
+use rand::Rng;
+use serde::Deserialize;
+use tcod::Color;
+
+use crate::{
+    components::{Ai, DeathCallback, Equipment, Fighter, Item, Slot},
+    object::Object,
+    rng::GameRng,
+    DUNGEON_DEPTH,
+};
+
+pub const MONSTERS_FILE: &str = "resources/monsters.json";
+pub const ITEMS_FILE: &str = "resources/items.json";
+
+static TEMPLATES: OnceLock<Templates> = OnceLock::new();
+
+/// What can spawn in the dungeon, as read from the files in `resources/`.
+#[derive(Debug)]
+pub struct Templates {
+    pub monsters: Vec<MonsterTemplate>,
+    pub items: Vec<ItemTemplate>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct MonsterTemplate {
+    pub name: String,
+    pub char: char,
+    pub color: Color,
+    pub fighter: FighterTemplate,
+    pub ai: Ai,
+    #[serde(default)]
+    pub swims: bool,
+    pub spawn_weight: Vec<Transition>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct FighterTemplate {
+    pub max_hp: i32,
+    pub defense: i32,
+    pub power: i32,
+    pub speed: i32,
+    pub xp: i32,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ItemTemplate {
+    pub name: String,
+    pub char: char,
+    pub color: Color,
+    pub item: Item,
+    #[serde(default)]
+    pub equipment: Option<EquipmentTemplate>,
+    pub spawn_weight: Vec<Transition>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct EquipmentTemplate {
+    pub slot: Slot,
+    #[serde(default)]
+    pub power_bonus: i32,
+    #[serde(default)]
+    pub defense_bonus: i32,
+    #[serde(default)]
+    pub max_hp_bonus: i32,
+}
+
+/// A value that changes as the player descends: it takes effect on `level`
+/// and lasts until the next transition.
+#[derive(Clone, Copy, Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Transition {
+    pub level: u32,
+    pub value: u32,
+}
+
+/// The value `table` has on dungeon `level`, or 0 before its first
+/// transition.
+pub fn from_dungeon_level(table: &[Transition], level: u32) -> u32 {
+    table
+        .iter()
+        .rev()
+        .find(|transition| level >= transition.level)
+        .map_or(0, |transition| transition.value)
+}
+
+/// Checks that a table's levels start at 1 or deeper and only go deeper.
+fn check_transitions(table: &[Transition]) -> Result<(), String> {
+    let mut previous = 0;
+    for transition in table {
+        if transition.level <= previous {
+            return Err(format!(
+                "the levels in spawn_weight must start at 1 and increase, but {} follows {}",
+                transition.level, previous
+            ));
+        }
+        previous = transition.level;
+    }
+    Ok(())
+}
+
+/// Finds a dungeon level on which none of the tables has any weight.
+fn level_without_spawns<'a, I>(tables: I) -> Option<u32>
+where
+    I: Iterator<Item = &'a [Transition]> + Clone,
+{
+    (1..=DUNGEON_DEPTH).find(|&level| {
+        tables
+            .clone()
+            .all(|table| from_dungeon_level(table, level) == 0)
+    })
+}
+
+/// Reads and validates the templates. Call this at startup, so that broken
+/// files are reported before anything is generated.
+pub fn load() -> Result<(), Box<dyn Error>> {
+    if TEMPLATES.get().is_none() {
+        let templates = read()?;
+        let _ = TEMPLATES.set(templates);
+    }
+    Ok(())
+}
+
+/// The templates read by `load`, reading them now if it was never called.
+pub fn templates() -> &'static Templates {
+    TEMPLATES.get_or_init(|| read().unwrap_or_else(|e| panic!("{}", e)))
+}
+
+fn read() -> Result<Templates, Box<dyn Error>> {
+    let monsters = read_file(MONSTERS_FILE)?;
+    let items = read_file(ITEMS_FILE)?;
+    Ok(Templates {
+        monsters: parse_monsters(MONSTERS_FILE, &monsters)?,
+        items: parse_items(ITEMS_FILE, &items)?,
+    })
+}
+
+fn read_file(path: &str) -> Result<String, Box<dyn Error>> {
+    fs::read_to_string(path).map_err(|e| format!("Could not read {}: {}", path, e).into())
+}
+
+/// Parses the monster templates in `text`; `path` is only used in errors.
+pub fn parse_monsters(path: &str, text: &str) -> Result<Vec<MonsterTemplate>, Box<dyn Error>> {
+    let monsters: Vec<MonsterTemplate> =
+        serde_json::from_str(text).map_err(|e| format!("{}: {}", path, e))?;
+    let mut names = HashSet::new();
+    for (index, monster) in monsters.iter().enumerate() {
+        let fail = |problem: &str| -> Result<(), Box<dyn Error>> {
+            Err(format!(
+                "{}, monster {} ({:?}): {}",
+                path,
+                index + 1,
+                monster.name,
+                problem
+            )
+            .into())
+        };
+        let fighter = &monster.fighter;
+        if monster.name.is_empty() {
+            fail("the name is empty")?;
+        }
+        if !names.insert(&monster.name) {
+            fail("another monster already has this name")?;
+        }
+        if fighter.max_hp <= 0 {
+            fail(&format!("max_hp must be positive, not {}", fighter.max_hp))?;
+        }
+        if fighter.speed <= 0 {
+            fail(&format!("speed must be positive, not {}", fighter.speed))?;
+        }
+        if fighter.defense < 0 || fighter.power < 0 || fighter.xp < 0 {
+            fail("defense, power and xp cannot be negative")?;
+        }
+        if let Err(problem) = check_transitions(&monster.spawn_weight) {
+            fail(&problem)?;
+        }
+        if monster.ai != Ai::Basic {
+            fail(&format!(
+                "{:?} is not an AI a monster can start with",
+                monster.ai
+            ))?;
+        }
+    }
+    let weights = monsters.iter().map(|monster| &monster.spawn_weight[..]);
+    if let Some(level) = level_without_spawns(weights) {
+        return Err(format!("{}: no monster can spawn on level {}", path, level).into());
+    }
+    Ok(monsters)
+}
+
+/// Parses the item templates in `text`; `path` is only used in errors.
+pub fn parse_items(path: &str, text: &str) -> Result<Vec<ItemTemplate>, Box<dyn Error>> {
+    let items: Vec<ItemTemplate> =
+        serde_json::from_str(text).map_err(|e| format!("{}: {}", path, e))?;
+    let mut names = HashSet::new();
+    for (index, item) in items.iter().enumerate() {
+        let fail = |problem: &str| -> Result<(), Box<dyn Error>> {
+            Err(format!(
+                "{}, item {} ({:?}): {}",
+                path,
+                index + 1,
+                item.name,
+                problem
+            )
+            .into())
+        };
+        if item.name.is_empty() {
+            fail("the name is empty")?;
+        }
+        if !names.insert(&item.name) {
+            fail("another item already has this name")?;
+        }
+        if let Err(problem) = check_transitions(&item.spawn_weight) {
+            fail(&problem)?;
+        }
+        match (&item.item, &item.equipment) {
+            (Item::Equipment, None) => fail("equipment needs an \"equipment\" entry")?,
+            (Item::Equipment, Some(_)) | (_, None) => {}
+            (_, Some(_)) => fail("only \"Equipment\" items can have an \"equipment\" entry")?,
+        }
+    }
+    let weights = items.iter().map(|item| &item.spawn_weight[..]);
+    if let Some(level) = level_without_spawns(weights) {
+        return Err(format!("{}: no item can spawn on level {}", path, level).into());
+    }
+    Ok(items)
+}
+
+/// Picks a monster to spawn on dungeon `level`.
+pub fn choose_monster(rng: &mut GameRng, level: u32) -> &'static MonsterTemplate {
+    choose_weighted(rng, &templates().monsters, |monster| {
+        from_dungeon_level(&monster.spawn_weight, level)
+    })
+}
+
+/// Picks an item to spawn on dungeon `level`.
+pub fn choose_item(rng: &mut GameRng, level: u32) -> &'static ItemTemplate {
+    choose_weighted(rng, &templates().items, |item| {
+        from_dungeon_level(&item.spawn_weight, level)
+    })
+}
+
+/// Picks one of `choices`, each as likely as its weight.
+pub fn choose_weighted<'a, T, W>(rng: &mut GameRng, choices: &'a [T], weight: W) -> &'a T
+where
+    W: Fn(&T) -> u32,
+{
+    let total: u32 = choices.iter().map(&weight).sum();
+    let mut roll = rng.gen_range(0, total);
+    for choice in choices {
+        if roll < weight(choice) {
+            return choice;
+        }
+        roll -= weight(choice);
+    }
+    unreachable!("the roll is below the total weight")
+}
+
+impl MonsterTemplate {
+    pub fn spawn(&self, x: i32, y: i32) -> Object {
+        let mut monster = Object::new(x, y, self.char, self.color, self.name.clone(), true, true);
+        monster.ai = Some(self.ai.clone());
+        monster.swims = self.swims;
+        monster.fighter = Some(Fighter {
+            base_max_hp: self.fighter.max_hp,
+            hp: self.fighter.max_hp,
+            base_defense: self.fighter.defense,
+            base_power: self.fighter.power,
+            speed: self.fighter.speed,
+            xp: self.fighter.xp,
+            on_death: DeathCallback::Monster,
+        });
+        monster
+    }
+}
+
+impl ItemTemplate {
+    pub fn spawn(&self, x: i32, y: i32) -> Object {
+        let mut item = Object::new(x, y, self.char, self.color, self.name.clone(), false, true);
+        item.item = Some(self.item.clone());
+        item.equipment = self
+            .equipment
+            .as_ref()
+            .map(|e| Equipment::new(e.slot, e.power_bonus, e.defense_bonus, e.max_hp_bonus));
+        item
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{MAX_ROOM_ITEMS, MAX_ROOM_MONSTERS};
+
+    #[test]
+    fn shipped_templates_are_valid() {
+        load().unwrap();
+        let templates = templates();
+        assert!(templates.monsters.iter().any(|m| m.name == "Orc"));
+        assert!(templates.items.iter().any(|i| i.item == Item::Heal));
+    }
+
+    #[test]
+    fn template_errors_point_at_the_offending_entry() {
+        let monsters = r#"[
+            {"name": "Orc", "char": "o", "color": {"r": 0, "g": 0, "b": 0},
+             "fighter": {"max_hp": 10, "defense": 0, "power": 3, "speed": 100, "xp": 35},
+             "ai": "Basic", "spawn_weight": [{"level": 1, "value": 80}]},
+            {"name": "Ghost", "char": "G", "color": {"r": 0, "g": 0, "b": 0},
+             "fighter": {"max_hp": 0, "defense": 0, "power": 3, "speed": 100, "xp": 35},
+             "ai": "Basic", "spawn_weight": [{"level": 3, "value": 5}]}
+        ]"#;
+        let error = parse_monsters("monsters.json", monsters).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "monsters.json, monster 2 (\"Ghost\"): max_hp must be positive, not 0"
+        );
+
+        let items = r#"[{"name": "Sword", "char": "/", "color": {"r": 0, "g": 0, "b": 0},
+            "item": "Equipment", "spawn_weight": [{"level": 1, "value": 5}]}]"#;
+        let error = parse_items("items.json", items).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "items.json, item 1 (\"Sword\"): equipment needs an \"equipment\" entry"
+        );
+
+        let trolls_only = r#"[
+            {"name": "Troll", "char": "T", "color": {"r": 0, "g": 0, "b": 0},
+             "fighter": {"max_hp": 16, "defense": 1, "power": 4, "speed": 100, "xp": 100},
+             "ai": "Basic", "spawn_weight": [{"level": 3, "value": 15}]}
+        ]"#;
+        let error = parse_monsters("monsters.json", trolls_only).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "monsters.json: no monster can spawn on level 1"
+        );
+
+        let error = parse_items("items.json", "[{\"name\": 3}]").unwrap_err();
+        assert!(error.to_string().starts_with("items.json: invalid type"));
+        assert!(error.to_string().contains("line 1"));
+    }
+
+    #[test]
+    fn spawn_tables_get_harder_deeper_in_the_dungeon() {
+        load().unwrap();
+        let mut rng = GameRng::new(42);
+        let mut troll_share = |level| {
+            let samples = 10_000;
+            let trolls = (0..samples)
+                .filter(|_| choose_monster(&mut rng, level).name == "Troll")
+                .count();
+            trolls as f32 / samples as f32
+        };
+        assert_eq!(troll_share(1), 0.0);
+        assert!((troll_share(3) - 15.0 / 115.0).abs() < 0.02);
+        assert!((troll_share(7) - 60.0 / 175.0).abs() < 0.02);
+
+        let items_at = |level| {
+            let mut rng = GameRng::new(7);
+            (0..1000)
+                .map(|_| choose_item(&mut rng, level).name.clone())
+                .collect::<Vec<_>>()
+        };
+        assert!(items_at(1).iter().all(|name| name == "Potion of Healing"));
+        assert!(items_at(6).iter().any(|name| name == "Scroll of fireball"));
+
+        assert!(
+            from_dungeon_level(MAX_ROOM_MONSTERS, 1) < from_dungeon_level(MAX_ROOM_MONSTERS, 6)
+        );
+        assert!(from_dungeon_level(MAX_ROOM_ITEMS, 1) < from_dungeon_level(MAX_ROOM_ITEMS, 7));
+    }
+}
@@ -0,0 +1,209 @@
+use std::collections::VecDeque;
+use std::fmt::Write as _;
+use std::io::{self, Read, Write};
+use std::mem;
+
+use tcod::input::KeyCode;
+
+use crate::{
+    frontend::{Frontend, Input, KeyPress},
+    render::{Canvas, CharGrid, Screen},
+    SCREEN_HEIGHT, SCREEN_WIDTH,
+};
+
+/// Ctrl-C and Ctrl-D: raw mode turns off the usual signals, so these close
+/// the "window" instead.
+const INTERRUPT: u8 = 0x03;
+const END_OF_INPUT: u8 = 0x04;
+
+/// Draws the game in a plain terminal with ANSI escape sequences and reads
+/// keys from stdin in raw mode, for when no window can be opened.
+pub struct TerminalFrontend {
+    screen: Screen<CharGrid, CharGrid>,
+    /// The last frame written out, so unchanged frames are not redrawn.
+    shown: Option<CharGrid>,
+    original_mode: libc::termios,
+    pending: VecDeque<KeyPress>,
+    closed: bool,
+}
+
+impl TerminalFrontend {
+    pub fn new() -> io::Result<Self> {
+        let (columns, rows) = terminal_size()?;
+        if columns < SCREEN_WIDTH || rows < SCREEN_HEIGHT {
+            return Err(io::Error::other(format!(
+                "the terminal is {}x{}, but needs to be at least {}x{}",
+                columns, rows, SCREEN_WIDTH, SCREEN_HEIGHT
+            )));
+        }
+        let original_mode = enable_raw_mode()?;
+        // switch to the alternate screen and hide the cursor
+        let mut stdout = io::stdout();
+        write!(stdout, "\x1b[?1049h\x1b[?25l")?;
+        stdout.flush()?;
+
+        Ok(TerminalFrontend {
+            screen: Screen::in_memory(),
+            shown: None,
+            original_mode: original_mode,
+            pending: VecDeque::new(),
+            closed: false,
+        })
+    }
+
+    /// Waits briefly for input and queues any keys it decodes.
+    fn read_keys(&mut self) {
+        let mut buffer = [0; 32];
+        match io::stdin().read(&mut buffer) {
+            Ok(read) => {
+                let bytes = &buffer[..read];
+                if bytes.contains(&INTERRUPT) || bytes.contains(&END_OF_INPUT) {
+                    self.closed = true;
+                }
+                self.pending.extend(decode_keys(bytes));
+            }
+            Err(_) => self.closed = true,
+        }
+    }
+}
+
+impl Drop for TerminalFrontend {
+    fn drop(&mut self) {
+        let mut stdout = io::stdout();
+        let _ = write!(stdout, "\x1b[0m\x1b[?25h\x1b[?1049l");
+        let _ = stdout.flush();
+        unsafe {
+            libc::tcsetattr(libc::STDIN_FILENO, libc::TCSANOW, &self.original_mode);
+        }
+    }
+}
+
+impl Frontend for TerminalFrontend {
+    type Root = CharGrid;
+    type Layer = CharGrid;
+
+    fn screen(&mut self) -> &mut Screen<CharGrid, CharGrid> {
+        &mut self.screen
+    }
+
+    fn flush(&mut self) {
+        if self.shown.as_ref() == Some(&self.screen.root) {
+            return;
+        }
+        let mut stdout = io::stdout();
+        let _ = stdout.write_all(ansi_frame(&self.screen.root).as_bytes());
+        let _ = stdout.flush();
+        self.shown = Some(self.screen.root.clone());
+    }
+
+    fn wait_for_keypress(&mut self) -> KeyPress {
+        loop {
+            if let Some(key) = self.pending.pop_front() {
+                return key;
+            }
+            if self.closed {
+                return Default::default();
+            }
+            self.read_keys();
+        }
+    }
+
+    fn check_for_event(&mut self) -> Option<Input> {
+        if self.pending.is_empty() && !self.closed {
+            self.read_keys();
+        }
+        self.pending.pop_front().map(Input::Key)
+    }
+
+    fn window_closed(&self) -> bool {
+        self.closed
+    }
+
+    fn toggle_fullscreen(&mut self) {
+        // the terminal emulator owns its window
+    }
+}
+
+fn terminal_size() -> io::Result<(i32, i32)> {
+    unsafe {
+        let mut size: libc::winsize = mem::zeroed();
+        if libc::ioctl(libc::STDOUT_FILENO, libc::TIOCGWINSZ, &mut size) != 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok((size.ws_col as i32, size.ws_row as i32))
+    }
+}
+
+/// Puts stdin into raw mode, returning the previous mode to restore later.
+/// Reads give up after a tenth of a second without input, which also paces
+/// the game loop.
+fn enable_raw_mode() -> io::Result<libc::termios> {
+    unsafe {
+        let mut original: libc::termios = mem::zeroed();
+        if libc::tcgetattr(libc::STDIN_FILENO, &mut original) != 0 {
+            return Err(io::Error::last_os_error());
+        }
+        let mut raw = original;
+        libc::cfmakeraw(&mut raw);
+        raw.c_cc[libc::VMIN] = 0;
+        raw.c_cc[libc::VTIME] = 1;
+        if libc::tcsetattr(libc::STDIN_FILENO, libc::TCSANOW, &raw) != 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(original)
+    }
+}
+
+/// Turns raw terminal input into key presses, understanding the escape
+/// sequences most terminals send for arrows and the navigation keys.
+pub fn decode_keys(bytes: &[u8]) -> Vec<KeyPress> {
+    let mut keys = vec![];
+    let mut rest = bytes;
+    while !rest.is_empty() {
+        let (key, length) = match rest {
+            [0x1b, b'[' | b'O', b'A', ..] => (Some(KeyPress::code(KeyCode::Up)), 3),
+            [0x1b, b'[' | b'O', b'B', ..] => (Some(KeyPress::code(KeyCode::Down)), 3),
+            [0x1b, b'[' | b'O', b'C', ..] => (Some(KeyPress::code(KeyCode::Right)), 3),
+            [0x1b, b'[' | b'O', b'D', ..] => (Some(KeyPress::code(KeyCode::Left)), 3),
+            [0x1b, b'[' | b'O', b'H', ..] => (Some(KeyPress::code(KeyCode::Home)), 3),
+            [0x1b, b'[' | b'O', b'F', ..] => (Some(KeyPress::code(KeyCode::End)), 3),
+            [0x1b, b'[', b'5', b'~', ..] => (Some(KeyPress::code(KeyCode::PageUp)), 4),
+            [0x1b, b'[', b'6', b'~', ..] => (Some(KeyPress::code(KeyCode::PageDown)), 4),
+            [0x1b, ..] => (Some(KeyPress::code(KeyCode::Escape)), 1),
+            [b'\r' | b'\n', ..] => (Some(KeyPress::code(KeyCode::Enter)), 1),
+            [b'\t', ..] => (Some(KeyPress::code(KeyCode::Tab)), 1),
+            [0x7f | 0x08, ..] => (Some(KeyPress::code(KeyCode::Backspace)), 1),
+            [byte, ..] if byte.is_ascii_graphic() || *byte == b' ' => {
+                (Some(KeyPress::text(*byte as char)), 1)
+            }
+            _ => (None, 1),
+        };
+        keys.extend(key);
+        rest = &rest[length..];
+    }
+    keys
+}
+
+/// The escape sequences that draw `root` over the whole terminal.
+fn ansi_frame(root: &CharGrid) -> String {
+    let mut frame = String::new();
+    let mut colours = None;
+    for y in 0..root.height() {
+        let _ = write!(frame, "\x1b[{};1H", y + 1);
+        for x in 0..root.width() {
+            let cell = root.cell(x, y);
+            if colours != Some((cell.foreground, cell.background)) {
+                let (fg, bg) = (cell.foreground, cell.background);
+                let _ = write!(
+                    frame,
+                    "\x1b[38;2;{};{};{};48;2;{};{};{}m",
+                    fg.r, fg.g, fg.b, bg.r, bg.g, bg.b
+                );
+                colours = Some((fg, bg));
+            }
+            frame.push(cell.glyph);
+        }
+    }
+    frame.push_str("\x1b[0m");
+    frame
+}
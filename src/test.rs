@@ -1,4 +1,39 @@
-use crate::{make_empty_map, tile::Tile};
+use std::collections::VecDeque;
+
+use tcod::{
+    colors::{DARKER_RED, LIGHT_RED, WHITE},
+    input::{KeyCode, Mouse},
+    map::Map as FovMap,
+    Color,
+};
+
+use crate::{
+    ai, can_level_up, choose_stat,
+    command::{self, Command},
+    components::{Ai, DeathCallback, Equipment, Fighter, Item, Slot, Stairs, Stat, UseResult},
+    dungeon,
+    frontend::{Frontend, Input, KeyPress},
+    game::Game,
+    handle_keys,
+    history::show_history,
+    initialise_fov,
+    inventory::{render_menu, use_item, use_item_command},
+    is_blocked, is_blocked_for, level_up, level_up_xp, make_empty_map, make_map,
+    messages::Messages,
+    move_by, new_game,
+    object::Object,
+    play_game, player_move_or_attack,
+    render::{CharGrid, Screen},
+    render_all,
+    replay::{load_replay, state_hash, Entry, Keyboard, Playback, Recorder, Replay},
+    rng::GameRng,
+    statusbar::render_bar,
+    targeting,
+    tile::{Tile, TileKind},
+    update_fov, Map, PlayerAction, PlayerInput, Tcod, ACTION_COST, CONFUSE_NUM_TURNS, DROP_COST,
+    FIREBALL_DAMAGE, FIREBALL_RADIUS, FOV_ALGORITHM, FOV_LIGHT_WALLS, LAVA_DAMAGE, MAP_HEIGHT,
+    MAP_WIDTH, MSG_X, NORMAL_SPEED, PANEL_Y, PICK_UP_COST, TORCH_RADIUS,
+};
 
 #[test]
 fn wall_set_test() {
@@ -7,3 +42,721 @@ fn wall_set_test() {
 
     assert!(map[30][22].blocked == true)
 }
+
+fn generate_dungeon(seed: u64) -> (Map, Vec<Object>) {
+    let player = Object::new(0, 0, '@', WHITE, "me".to_string(), true, true);
+    let mut objects = vec![player];
+    let mut rng = GameRng::new(seed);
+    let map = make_map(&mut objects, 1, &mut rng);
+    (map, objects)
+}
+
+#[test]
+fn same_seed_generates_same_dungeon() {
+    assert_eq!(generate_dungeon(1234), generate_dungeon(1234));
+}
+
+#[test]
+fn different_seeds_generate_different_dungeons() {
+    assert_ne!(generate_dungeon(1234), generate_dungeon(4321));
+}
+
+#[test]
+fn stairs_down_are_placed_on_every_level() {
+    let (mut game, mut objects) = new_game(7);
+    assert!(objects.iter().any(|o| o.stairs == Some(Stairs::Down)));
+    assert!(!objects.iter().any(|o| o.stairs == Some(Stairs::Up)));
+
+    dungeon::enter_level(&mut game, &mut objects, 2);
+    assert_eq!(game.dungeon_level, 2);
+    assert!(objects.iter().any(|o| o.stairs == Some(Stairs::Down)));
+    assert_eq!(dungeon::stairs_under_player(&objects), Some(Stairs::Up));
+}
+
+#[test]
+fn going_back_up_restores_the_previous_level() {
+    let (mut game, mut objects) = new_game(99);
+    game.map[1][1].explored = true;
+    objects[1].name = "wounded survivor".to_string();
+    let map_before = game.map.clone();
+    let names_before: Vec<_> = objects.iter().skip(1).map(|o| o.name.clone()).collect();
+
+    dungeon::enter_level(&mut game, &mut objects, 2);
+    assert_ne!(game.map, map_before);
+    dungeon::enter_level(&mut game, &mut objects, 1);
+
+    assert_eq!(game.map, map_before);
+    let names_after: Vec<_> = objects.iter().skip(1).map(|o| o.name.clone()).collect();
+    assert_eq!(names_after, names_before);
+    assert_eq!(dungeon::stairs_under_player(&objects), Some(Stairs::Down));
+    assert!(game.levels.contains_key(&2));
+}
+
+#[test]
+fn taking_the_stairs_takes_a_turn() {
+    let (mut game, mut objects) = new_game(7);
+    let mut fov = FovMap::new(MAP_WIDTH, MAP_HEIGHT);
+    let mut run = |command| command::execute(command, &mut fov, &mut game, &mut objects);
+
+    // the player starts off the stairs, and going nowhere is free
+    assert_eq!(run(Command::Descend), PlayerAction::DidntTakeTurn);
+    assert_eq!(run(Command::Ascend), PlayerAction::DidntTakeTurn);
+    assert_eq!(game.dungeon_level, 1);
+
+    let stairs = objects
+        .iter()
+        .find(|o| o.stairs == Some(Stairs::Down))
+        .unwrap()
+        .pos();
+    objects[0].set_pos(stairs.0, stairs.1);
+    let mut run = |command| command::execute(command, &mut fov, &mut game, &mut objects);
+    assert_eq!(run(Command::Ascend), PlayerAction::DidntTakeTurn);
+    assert_eq!(run(Command::Descend), PlayerAction::TookTurn(ACTION_COST));
+    assert_eq!(run(Command::Descend), PlayerAction::DidntTakeTurn);
+    assert_eq!(run(Command::Ascend), PlayerAction::TookTurn(ACTION_COST));
+    assert_eq!(game.dungeon_level, 1);
+}
+
+pub fn fighter_with_speed(speed: i32) -> Object {
+    let mut object = Object::new(0, 0, 'x', WHITE, format!("speed {}", speed), true, true);
+    object.fighter = Some(Fighter {
+        base_max_hp: 10,
+        hp: 10,
+        base_defense: 0,
+        base_power: 0,
+        speed: speed,
+        xp: 0,
+        on_death: DeathCallback::Monster,
+    });
+    object.ai = Some(Ai::Basic);
+    object
+}
+
+#[test]
+fn bar_is_filled_in_proportion_to_its_value() {
+    let mut panel = CharGrid::new(12, 1);
+    render_bar(&mut panel, 1, 0, 10, "HP", 5, 10, LIGHT_RED, DARKER_RED);
+
+    assert_eq!(panel.row(0), "  HP: 5/10");
+    let backgrounds: Vec<_> = (1..11).map(|x| panel.cell(x, 0).background).collect();
+    assert_eq!(backgrounds[..5], [LIGHT_RED; 5]);
+    assert_eq!(backgrounds[5..], [DARKER_RED; 5]);
+}
+
+#[test]
+fn menu_is_drawn_centred_with_lettered_options() {
+    let mut screen = Screen::in_memory();
+    render_menu(
+        "Pick one:",
+        &["Potion of Healing", "Scroll"],
+        24,
+        &mut screen,
+    );
+
+    assert_eq!(screen.root.row(23), "");
+    assert_eq!(screen.root.row(24), format!("{:28}Pick one:", ""));
+    assert_eq!(
+        screen.root.row(25),
+        format!("{:28}(a) Potion of Healing", "")
+    );
+    assert_eq!(screen.root.row(26), format!("{:28}(b) Scroll", ""));
+}
+
+#[test]
+fn full_frame_renders_without_a_window() {
+    let (mut game, mut objects) = new_game(8);
+    game.map = make_empty_map();
+    objects.truncate(1);
+    objects[0].set_pos(10, 10);
+    let orc = Object::new(12, 10, 'o', WHITE, "orc".to_string(), true, true);
+    objects.push(orc);
+    game.messages.add("The orc glares at you.", WHITE);
+
+    let mut screen = Screen::in_memory();
+    let mut fov = FovMap::new(MAP_WIDTH, MAP_HEIGHT);
+    initialise_fov(&mut fov, &game.map);
+    render_all(
+        &mut screen,
+        &mut fov,
+        Mouse::default(),
+        &mut game,
+        &objects,
+        true,
+    );
+
+    assert_eq!(screen.root.row(10), format!("{:10}@ o", ""));
+    assert!(game.map[10][10].explored);
+    assert_eq!(screen.root.row(PANEL_Y + 1), "       HP: 30/30");
+    assert_eq!(screen.root.row(PANEL_Y + 3), " Dungeon level: 1");
+    let last_message = screen.root.row(PANEL_Y + 5);
+    assert_eq!(last_message.trim_start(), "The orc glares at you.");
+    assert_eq!(last_message.len() as i32, MSG_X + 22);
+}
+
+#[cfg(unix)]
+#[test]
+fn terminal_input_is_decoded_into_key_presses() {
+    let keys = crate::terminal::decode_keys(b"g\x1b[A\x1b\x1bOD\r");
+    assert_eq!(
+        keys,
+        vec![
+            KeyPress::text('g'),
+            KeyPress::code(KeyCode::Up),
+            KeyPress::code(KeyCode::Escape),
+            KeyPress::code(KeyCode::Left),
+            KeyPress::code(KeyCode::Enter),
+        ]
+    );
+}
+
+/// Draws in memory and plays back scripted input. Once the script runs out
+/// the "window" counts as closed, so no loop can wait forever.
+struct ScriptedFrontend {
+    screen: Screen<CharGrid, CharGrid>,
+    inputs: VecDeque<Input>,
+}
+
+impl Frontend for ScriptedFrontend {
+    type Root = CharGrid;
+    type Layer = CharGrid;
+
+    fn screen(&mut self) -> &mut Screen<CharGrid, CharGrid> {
+        &mut self.screen
+    }
+
+    fn flush(&mut self) {}
+
+    fn wait_for_keypress(&mut self) -> KeyPress {
+        match self.inputs.pop_front() {
+            Some(Input::Key(key)) => key,
+            _ => Default::default(),
+        }
+    }
+
+    fn check_for_event(&mut self) -> Option<Input> {
+        self.inputs.pop_front()
+    }
+
+    fn window_closed(&self) -> bool {
+        self.inputs.is_empty()
+    }
+
+    fn toggle_fullscreen(&mut self) {}
+}
+
+/// An open level with the player at (10, 10) and a monster at each of
+/// `monsters`.
+fn arena(
+    monsters: &[(i32, i32)],
+    inputs: Vec<Input>,
+) -> (Tcod<ScriptedFrontend>, Game, Vec<Object>) {
+    let (mut game, mut objects) = new_game(11);
+    game.map = make_empty_map();
+    objects.truncate(1);
+    objects[0].set_pos(10, 10);
+    for &(x, y) in monsters {
+        let mut orc = fighter_with_speed(NORMAL_SPEED);
+        orc.set_pos(x, y);
+        orc.name = "orc".to_string();
+        objects.push(orc);
+    }
+
+    let mut fov = FovMap::new(MAP_WIDTH, MAP_HEIGHT);
+    initialise_fov(&mut fov, &game.map);
+    fov.compute_fov(10, 10, TORCH_RADIUS, FOV_LIGHT_WALLS, FOV_ALGORITHM);
+    let tcod = Tcod {
+        frontend: ScriptedFrontend {
+            screen: Screen::in_memory(),
+            inputs: inputs.into(),
+        },
+        fov: fov,
+        key: Default::default(),
+        mouse: Default::default(),
+    };
+    (tcod, game, objects)
+}
+
+fn scroll(item: Item) -> Object {
+    let mut scroll = Object::new(0, 0, '#', WHITE, "scroll".to_string(), false, true);
+    scroll.item = Some(item);
+    scroll
+}
+
+#[test]
+fn lightning_bolt_strikes_the_closest_monster() {
+    let (tcod, mut game, mut objects) = arena(&[(14, 10), (12, 11)], vec![]);
+    game.inventory.push(scroll(Item::Lightning));
+
+    let result = use_item(0, None, &tcod.fov, &mut game, &mut objects);
+    assert_eq!(result, UseResult::UsedUp);
+    assert!(game.inventory.is_empty());
+    assert!(objects[1].fighter.is_some());
+    assert_eq!(objects[2].fighter, None);
+    assert_eq!(objects[2].name, "remains of orc");
+}
+
+#[test]
+fn fireball_burns_every_fighter_in_its_radius_including_the_player() {
+    let (tcod, mut game, mut objects) = arena(&[(12, 10), (13, 12), (15, 10)], vec![]);
+    game.inventory.push(scroll(Item::Fireball));
+    objects[1].fighter.as_mut().unwrap().xp = 35;
+    objects[2].fighter.as_mut().unwrap().hp = 100;
+
+    let target = Some((11, 10));
+    let result = use_item(0, target, &tcod.fov, &mut game, &mut objects);
+    assert_eq!(result, UseResult::UsedUp);
+    assert!(objects[3].distance(11, 10) > FIREBALL_RADIUS as f32);
+    assert_eq!(objects[0].fighter.unwrap().hp, 30 - FIREBALL_DAMAGE);
+    assert_eq!(objects[1].name, "remains of orc");
+    assert_eq!(objects[2].fighter.unwrap().hp, 100 - FIREBALL_DAMAGE);
+    assert_eq!(objects[3].fighter.unwrap().hp, 10);
+    assert_eq!(objects[0].fighter.unwrap().xp, 35);
+}
+
+#[test]
+fn fireballs_and_confusion_refuse_targets_out_of_sight_or_range() {
+    let (mut tcod, mut game, mut objects) = arena(&[(30, 30), (19, 10)], vec![]);
+    assert!(!tcod.fov.is_in_fov(30, 30));
+    assert!(tcod.fov.is_in_fov(19, 10));
+    game.inventory.push(scroll(Item::Fireball));
+    game.inventory.push(scroll(Item::Confuse));
+
+    let mut run = |command| command::execute(command, &mut tcod.fov, &mut game, &mut objects);
+    let fireball = Command::UseItem {
+        slot: 0,
+        target: Some((30, 30)),
+    };
+    assert_eq!(run(fireball), PlayerAction::DidntTakeTurn);
+    let confuse = |target| Command::UseItem {
+        slot: 1,
+        target: Some(target),
+    };
+    assert_eq!(run(confuse((30, 30))), PlayerAction::DidntTakeTurn);
+    // in sight, but further than CONFUSE_RANGE
+    assert_eq!(run(confuse((19, 10))), PlayerAction::DidntTakeTurn);
+    assert_eq!(game.inventory.len(), 2);
+    assert_eq!(objects[1].fighter.unwrap().hp, 10);
+    assert_eq!(objects[2].ai, Some(Ai::Basic));
+}
+
+#[test]
+fn confusion_wears_off_after_some_turns() {
+    let click = Mouse {
+        cx: 12,
+        cy: 10,
+        lbutton_pressed: true,
+        ..Default::default()
+    };
+    let (mut tcod, mut game, mut objects) = arena(&[(12, 10)], vec![Input::Mouse(click)]);
+    game.inventory.push(scroll(Item::Confuse));
+
+    let command = use_item_command(0, &mut tcod, &mut game, &objects);
+    let target = Some((12, 10));
+    assert_eq!(command, Some(Command::UseItem { slot: 0, target }));
+    let result = use_item(0, target, &tcod.fov, &mut game, &mut objects);
+    assert_eq!(result, UseResult::UsedUp);
+    assert!(matches!(objects[1].ai, Some(Ai::Confused { .. })));
+
+    for _ in 0..CONFUSE_NUM_TURNS {
+        ai::ai_take_turn(1, &tcod.fov, &mut game, &mut objects);
+        assert!(matches!(objects[1].ai, Some(Ai::Confused { .. })));
+    }
+    ai::ai_take_turn(1, &tcod.fov, &mut game, &mut objects);
+    assert_eq!(objects[1].ai, Some(Ai::Basic));
+}
+
+fn key(code: KeyCode) -> Input {
+    Input::Key(KeyPress::code(code))
+}
+
+#[test]
+fn targeting_cycles_through_monsters_and_follows_the_movement_keys() {
+    let inputs = vec![key(KeyCode::Tab), key(KeyCode::Enter)];
+    let (mut tcod, mut game, objects) = arena(&[(12, 10), (10, 14)], inputs);
+    let target = targeting::target_monster(&mut tcod, &mut game, &objects, None);
+    assert_eq!(target, Some(2));
+
+    let inputs = vec![key(KeyCode::Right), key(KeyCode::Down), key(KeyCode::Enter)];
+    tcod.frontend.inputs = inputs.into();
+    let target = targeting::target_tile(&mut tcod, &mut game, &objects, None);
+    assert_eq!(target, Some((13, 11)));
+
+    // vi keys, diagonals and the numpad move the cursor too
+    let inputs = vec![
+        Input::Key(KeyPress::text('l')),
+        Input::Key(KeyPress::text('n')),
+        key(KeyCode::NumPad7),
+        key(KeyCode::Enter),
+    ];
+    let (mut tcod, mut game, objects) = arena(&[], inputs);
+    let target = targeting::look(&mut tcod, &mut game, &objects);
+    assert_eq!(target, Some((11, 10)));
+}
+
+#[test]
+fn clicking_targets_the_tile_under_the_pointer_even_without_moving_it() {
+    let click = Mouse {
+        cx: 10,
+        cy: 13,
+        lbutton_pressed: true,
+        ..Default::default()
+    };
+    let (mut tcod, mut game, objects) = arena(&[(12, 10)], vec![Input::Mouse(click)]);
+    tcod.mouse = Mouse {
+        lbutton_pressed: false,
+        ..click
+    };
+    let target = targeting::target_tile(&mut tcod, &mut game, &objects, None);
+    assert_eq!(target, Some((10, 13)));
+}
+
+#[test]
+fn targets_out_of_range_or_line_of_fire_are_refused() {
+    // the second orc hides behind the first one
+    let inputs = vec![
+        key(KeyCode::Tab),
+        key(KeyCode::Enter),
+        key(KeyCode::Tab),
+        key(KeyCode::Enter),
+    ];
+    let (mut tcod, mut game, objects) = arena(&[(12, 10), (15, 10)], inputs);
+    let target = targeting::target_tile(&mut tcod, &mut game, &objects, Some(6.0));
+    assert_eq!(target, Some((12, 10)));
+
+    tcod.frontend.inputs = vec![key(KeyCode::Enter)].into();
+    let target = targeting::target_tile(&mut tcod, &mut game, &objects, Some(1.5));
+    assert_eq!(target, None);
+}
+
+fn gear(name: &str, equipment: Equipment) -> Object {
+    let mut gear = Object::new(0, 0, '[', WHITE, name.to_string(), false, true);
+    gear.item = Some(Item::Equipment);
+    gear.equipment = Some(equipment);
+    gear
+}
+
+#[test]
+fn equipment_adds_to_stats_while_worn_and_shares_a_slot() {
+    let (tcod, mut game, mut objects) = arena(&[], vec![]);
+    game.inventory
+        .push(gear("Sword", Equipment::new(Slot::MainHand, 3, 0, 0)));
+    game.inventory
+        .push(gear("Axe", Equipment::new(Slot::MainHand, 4, 0, 0)));
+    game.inventory
+        .push(gear("Ring", Equipment::new(Slot::Ring, 0, 1, 10)));
+    let (power, defense) = (objects[0].power(), objects[0].defense());
+
+    use_item(0, None, &tcod.fov, &mut game, &mut objects);
+    use_item(2, None, &tcod.fov, &mut game, &mut objects);
+    assert_eq!(objects[0].power(), power + 3);
+    assert_eq!(objects[0].defense(), defense + 1);
+    assert_eq!(objects[0].max_hp(), 40);
+    objects[0].heal(10);
+
+    // the axe takes the sword's place, and the ring comes off again
+    let result = use_item(1, None, &tcod.fov, &mut game, &mut objects);
+    assert_eq!(result, UseResult::UsedAndKept);
+    use_item(2, None, &tcod.fov, &mut game, &mut objects);
+    assert_eq!(game.inventory.len(), 3);
+    assert_eq!(game.inventory[0].equipment.unwrap().equipped, false);
+    assert_eq!(objects[0].power(), power + 4);
+    assert_eq!(objects[0].max_hp(), 30);
+    assert_eq!(objects[0].fighter.unwrap().hp, 30);
+
+    // the bonuses go with the wearer, not with whichever object is first
+    let player = objects.remove(0);
+    assert_eq!(player.power(), power + 4);
+    assert_eq!(player.worn.len(), 1);
+}
+
+#[test]
+fn kills_earn_experience_that_levels_the_player_up() {
+    let choose_strength = Input::Key(KeyPress::text('b'));
+    let (mut tcod, mut game, mut objects) = arena(&[(11, 10)], vec![choose_strength]);
+    objects[1].fighter.as_mut().unwrap().hp = 1;
+    objects[1].fighter.as_mut().unwrap().xp = 35;
+    objects[0].fighter.as_mut().unwrap().xp = level_up_xp(1) - 30;
+
+    player_move_or_attack(0, 1, 0, &mut game, &mut objects);
+    assert!(!objects[1].is_alive);
+    assert_eq!(objects[0].fighter.unwrap().xp, level_up_xp(1) + 5);
+
+    let power = objects[0].power();
+    assert_eq!(choose_stat(&mut tcod, &objects), Some(Stat::Strength));
+    level_up(Stat::Strength, &mut game, &mut objects);
+    assert_eq!(objects[0].level, 2);
+    assert_eq!(objects[0].power(), power + 1);
+    assert_eq!(objects[0].fighter.unwrap().xp, 5);
+
+    // without the experience for another level, choosing a stat does nothing
+    assert!(!can_level_up(&objects[0]));
+    level_up(Stat::Agility, &mut game, &mut objects);
+    assert_eq!(objects[0].level, 2);
+}
+
+#[test]
+fn doors_open_when_bumped_and_close_on_command() {
+    let (mut tcod, mut game, mut objects) = arena(&[], vec![]);
+    game.map[11][10] = Tile::door();
+    initialise_fov(&mut tcod.fov, &game.map);
+
+    player_move_or_attack(0, 1, 0, &mut game, &mut objects);
+    assert_eq!(objects[0].pos(), (10, 10));
+    assert!(!game.map[11][10].blocked);
+    assert!(update_fov(&mut tcod.fov, &mut game));
+    assert!(tcod.fov.is_transparent(11, 10) && tcod.fov.is_walkable(11, 10));
+
+    // nothing closes while the player stands in the doorway
+    player_move_or_attack(0, 1, 0, &mut game, &mut objects);
+    tcod.key = KeyPress::text('c');
+    let input = handle_keys(&mut tcod, 0, &mut objects, &mut game);
+    assert_eq!(input, PlayerInput::Nothing);
+
+    player_move_or_attack(0, -1, 0, &mut game, &mut objects);
+    let input = handle_keys(&mut tcod, 0, &mut objects, &mut game);
+    assert_eq!(input, PlayerInput::Command(Command::CloseDoor(1, 0)));
+    let action = command::execute(
+        Command::CloseDoor(1, 0),
+        &mut tcod.fov,
+        &mut game,
+        &mut objects,
+    );
+    assert_eq!(action, PlayerAction::TookTurn(ACTION_COST));
+    assert!(game.map[11][10].is_closed_door());
+    assert!(update_fov(&mut tcod.fov, &mut game));
+    assert!(!tcod.fov.is_transparent(11, 10));
+    assert!(!update_fov(&mut tcod.fov, &mut game));
+}
+
+#[test]
+fn monsters_open_doors_on_their_way() {
+    let (mut tcod, mut game, mut objects) = arena(&[(12, 10)], vec![]);
+    for y in 0..MAP_HEIGHT {
+        game.map[11][y as usize] = Tile::wall();
+    }
+    game.map[11][10] = Tile::door();
+
+    ai::ai_take_turn(1, &tcod.fov, &mut game, &mut objects);
+    assert_eq!(objects[1].pos(), (12, 10));
+    assert!(game.map[11][10].is_open_door());
+    update_fov(&mut tcod.fov, &mut game);
+    ai::ai_take_turn(1, &tcod.fov, &mut game, &mut objects);
+    assert_eq!(objects[1].pos(), (11, 10));
+}
+
+#[test]
+fn lava_burns_and_deep_water_stops_non_swimmers() {
+    let (_, mut game, mut objects) = arena(&[(10, 13)], vec![]);
+    game.map[11][10] = Tile::new(TileKind::Lava);
+    game.map[12][10] = Tile::new(TileKind::DeepWater);
+    game.map[10][12] = Tile::new(TileKind::DeepWater);
+    let hp = objects[0].fighter.unwrap().hp;
+
+    player_move_or_attack(0, 1, 0, &mut game, &mut objects);
+    assert_eq!(objects[0].pos(), (11, 10));
+    assert_eq!(objects[0].fighter.unwrap().hp, hp - LAVA_DAMAGE);
+
+    player_move_or_attack(0, 1, 0, &mut game, &mut objects);
+    assert_eq!(objects[0].pos(), (11, 10));
+
+    objects[1].swims = true;
+    move_by(1, 0, -1, &mut game, &mut objects);
+    assert_eq!(objects[1].pos(), (10, 12));
+
+    assert!(is_blocked(12, 10, &game.map, &objects));
+    assert!(!is_blocked_for(true, 12, 10, &game.map, &objects));
+}
+
+#[test]
+fn grass_hides_what_is_behind_it_and_rubble_slows_walkers() {
+    let grass = Tile::new(TileKind::Grass);
+    assert!(grass.block_sight && !grass.blocked);
+
+    let (_, mut game, mut objects) = arena(&[], vec![]);
+    game.map[11][10] = Tile::new(TileKind::Rubble);
+    objects[0].energy = 0;
+    player_move_or_attack(0, 1, 0, &mut game, &mut objects);
+    assert_eq!(objects[0].pos(), (11, 10));
+    assert_eq!(objects[0].energy, -ACTION_COST);
+}
+
+#[test]
+fn message_history_scrolls_and_searches() {
+    let (mut tcod, mut game, _) = arena(&[], vec![]);
+    game.messages = Messages::new();
+    for n in 1..=60 {
+        game.messages.add(format!("Message {}", n), WHITE);
+        game.messages.next_turn();
+    }
+    let bottom = crate::SCREEN_HEIGHT - 1;
+
+    // the newest messages fill the screen, with the turns they were logged on
+    show_history(&mut tcod, &game.messages);
+    let root = &tcod.frontend.screen.root;
+    assert_eq!(root.row(0), "Message log: 60 messages");
+    assert_eq!(root.row(2), "   13  Message 13");
+    assert_eq!(root.row(bottom), "   60  Message 60");
+
+    tcod.frontend.inputs = vec![key(KeyCode::PageUp), key(KeyCode::Down)].into();
+    show_history(&mut tcod, &game.messages);
+    // paging up stops at the oldest message
+    assert_eq!(tcod.frontend.screen.root.row(2), "    2  Message 2");
+    assert_eq!(tcod.frontend.screen.root.row(bottom), "   49  Message 49");
+
+    let inputs = [
+        KeyPress::text('/'),
+        KeyPress::text('5'),
+        KeyPress::code(KeyCode::Enter),
+    ];
+    tcod.frontend.inputs = inputs.iter().map(|&k| Input::Key(k)).collect();
+    show_history(&mut tcod, &game.messages);
+    let root = &tcod.frontend.screen.root;
+    assert_eq!(root.row(0), "Message log: 15 of 60 messages match");
+    assert_eq!(root.row(bottom), "   59  Message 59");
+    assert_eq!(root.row(bottom - 14), "    5  Message 5");
+}
+
+#[test]
+fn repeated_messages_collapse_and_older_turns_fade() {
+    let (_, mut game, objects) = arena(&[], vec![]);
+    let red = Color {
+        r: 200,
+        g: 60,
+        b: 60,
+    };
+    for _ in 0..3 {
+        game.messages.add("The orc hits you.", red);
+    }
+    game.messages.next_turn();
+    game.messages.next_turn();
+    game.messages.add("You hit the orc.", red);
+    assert_eq!(game.messages.len(), 3);
+
+    let mut screen = Screen::in_memory();
+    let mut fov = FovMap::new(MAP_WIDTH, MAP_HEIGHT);
+    render_all(
+        &mut screen,
+        &mut fov,
+        Mouse::default(),
+        &mut game,
+        &objects,
+        true,
+    );
+
+    let repeated = screen.root.row(PANEL_Y + 4);
+    assert_eq!(&repeated[MSG_X as usize..], "The orc hits you. (x3)");
+    assert_eq!(
+        screen.root.row(PANEL_Y + 5).trim_start(),
+        "You hit the orc."
+    );
+    let faded = Color {
+        r: 100,
+        g: 30,
+        b: 30,
+    };
+    assert_eq!(screen.root.cell(MSG_X, PANEL_Y + 4).foreground, faded);
+    assert_eq!(screen.root.cell(MSG_X, PANEL_Y + 5).foreground, red);
+}
+
+#[test]
+fn keys_become_commands_including_diagonal_moves_and_waiting() {
+    let (mut tcod, mut game, mut objects) = arena(&[], vec![]);
+    let mut command_for = |key: KeyPress| {
+        tcod.key = key;
+        handle_keys(&mut tcod, 0, &mut objects, &mut game)
+    };
+    let up_left = command_for(KeyPress::code(KeyCode::NumPad7));
+    assert_eq!(up_left, PlayerInput::Command(Command::Move(-1, -1)));
+    let down_right = command_for(KeyPress::text('n'));
+    assert_eq!(down_right, PlayerInput::Command(Command::Move(1, 1)));
+    let wait = command_for(KeyPress::text('.'));
+    assert_eq!(wait, PlayerInput::Command(Command::Wait));
+    let exit = command_for(KeyPress::code(KeyCode::Escape));
+    assert_eq!(exit, PlayerInput::Exit);
+}
+
+#[test]
+fn commands_drive_the_player_without_any_input() {
+    let (mut tcod, mut game, mut objects) = arena(&[], vec![]);
+    game.inventory
+        .push(gear("Ring", Equipment::new(Slot::Ring, 0, 0, 10)));
+    game.inventory[0].equip(&mut objects[0], &mut game.messages);
+    objects[0].heal(10);
+    let mut run = |command| command::execute(command, &mut tcod.fov, &mut game, &mut objects);
+
+    assert_eq!(
+        run(Command::Move(-1, -1)),
+        PlayerAction::TookTurn(ACTION_COST)
+    );
+    assert_eq!(run(Command::Wait), PlayerAction::TookTurn(ACTION_COST));
+    assert_eq!(run(Command::Move(2, 0)), PlayerAction::DidntTakeTurn);
+    assert_eq!(run(Command::Move(0, 0)), PlayerAction::DidntTakeTurn);
+    assert_eq!(run(Command::Drop(3)), PlayerAction::DidntTakeTurn);
+    assert_eq!(run(Command::Drop(0)), PlayerAction::TookTurn(DROP_COST));
+    assert_eq!(run(Command::PickUp), PlayerAction::TookTurn(PICK_UP_COST));
+    assert_eq!(run(Command::PickUp), PlayerAction::DidntTakeTurn);
+    assert_eq!(run(Command::Descend), PlayerAction::DidntTakeTurn);
+
+    // the ring came off when it was dropped, taking its bonus HP along
+    assert_eq!(objects[0].pos(), (9, 9));
+    assert_eq!(objects[0].fighter.unwrap().hp, 30);
+    assert_eq!(game.inventory[0].equipment.unwrap().equipped, false);
+
+    let command = Command::UseItem {
+        slot: 1,
+        target: Some((3, 4)),
+    };
+    let json = serde_json::to_string(&command).unwrap();
+    assert_eq!(serde_json::from_str::<Command>(&json).unwrap(), command);
+}
+
+#[test]
+fn recorded_games_replay_to_the_same_state() {
+    let seed = 7;
+    let keys = "lljjkh.l.hhkyb".chars();
+    let (mut tcod, _, _) = arena(&[], keys.map(|c| Input::Key(KeyPress::text(c))).collect());
+    let (mut game, mut objects) = new_game(seed);
+    let path = std::env::temp_dir().join(format!("replay-{}", std::process::id()));
+    let path = path.to_str().unwrap();
+    let mut keyboard = Keyboard::default();
+    keyboard.start_recording(path, seed);
+    play_game(&mut tcod, &mut game, &mut objects, &mut keyboard);
+    keyboard.record_state(&game, &objects);
+
+    let replay = load_replay(path).unwrap();
+    // continuing a game only adds to its own replay
+    assert!(Recorder::resume(path, seed).is_ok());
+    assert!(Recorder::resume(path, seed + 1).is_err());
+    std::fs::remove_file(path).unwrap();
+    assert_eq!(replay.seed, seed);
+    assert!(matches!(
+        replay.entries[0],
+        Entry::Command(Command::Move(1, 0))
+    ));
+    assert_eq!(
+        replay.entries.last(),
+        Some(&Entry::StateHash(state_hash(&game, &objects)))
+    );
+
+    let play_back = |replay: Replay| {
+        let idle = vec![Input::Mouse(Mouse::default()); 40];
+        let (mut tcod, _, _) = arena(&[], idle);
+        let (mut game, mut objects) = new_game(replay.seed);
+        let mut playback = Playback::new(replay);
+        playback.speed = 0;
+        play_game(&mut tcod, &mut game, &mut objects, &mut playback);
+        playback.finish(&game, &objects);
+        (
+            playback.matched,
+            playback.mismatched,
+            state_hash(&game, &objects),
+        )
+    };
+    let entries = replay.entries.clone();
+    assert_eq!(play_back(replay), (1, 0, state_hash(&game, &objects)));
+    let (_, mismatched, _) = play_back(Replay {
+        seed: seed + 1,
+        entries: entries,
+    });
+    assert_eq!(mismatched, 1);
+}
@@ -1,24 +1,142 @@
-#[derive(Clone, Copy, Debug)]
+use serde::{Deserialize, Serialize};
+use tcod::{
+    colors::{DARK_GREEN, GREY, LIGHT_SKY, SKY, YELLOW},
+    Color,
+};
+
+use crate::{
+    ACTION_COST, COLOR_DARK_DEEP_WATER, COLOR_DARK_GROUND, COLOR_DARK_LAVA, COLOR_DARK_WALL,
+    COLOR_DARK_WATER, COLOR_DOOR, COLOR_LIGHT_DEEP_WATER, COLOR_LIGHT_GROUND, COLOR_LIGHT_LAVA,
+    COLOR_LIGHT_WALL, COLOR_LIGHT_WATER,
+};
+
+/// What a tile is made of, which decides how it looks and what it does to
+/// whoever walks on it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TileKind {
+    Floor,
+    Wall,
+    Door,
+    /// Blocks sight but not movement.
+    Grass,
+    /// Slow to climb over.
+    Rubble,
+    /// Shallow enough to wade through, slowly.
+    Water,
+    /// Only swimmers can get through.
+    DeepWater,
+    /// Burns whoever steps in it.
+    Lava,
+}
+
+impl TileKind {
+    /// The background colours in and out of view.
+    pub fn colors(self) -> (Color, Color) {
+        use TileKind::*;
+        match self {
+            Wall => (COLOR_LIGHT_WALL, COLOR_DARK_WALL),
+            Floor | Door | Grass | Rubble => (COLOR_LIGHT_GROUND, COLOR_DARK_GROUND),
+            Water => (COLOR_LIGHT_WATER, COLOR_DARK_WATER),
+            DeepWater => (COLOR_LIGHT_DEEP_WATER, COLOR_DARK_DEEP_WATER),
+            Lava => (COLOR_LIGHT_LAVA, COLOR_DARK_LAVA),
+        }
+    }
+
+    pub fn blocks_motion(self) -> bool {
+        matches!(self, TileKind::Wall | TileKind::Door)
+    }
+
+    pub fn blocks_sight(self) -> bool {
+        matches!(self, TileKind::Wall | TileKind::Door | TileKind::Grass)
+    }
+
+    /// The energy it takes to step onto the tile.
+    pub fn move_cost(self) -> i32 {
+        match self {
+            TileKind::Rubble | TileKind::DeepWater => 2 * ACTION_COST,
+            TileKind::Water => ACTION_COST * 3 / 2,
+            _ => ACTION_COST,
+        }
+    }
+
+    /// The energy a path across the tile is reckoned to take, so monsters
+    /// walk around slow ground and only wade into lava when there is no
+    /// other way.
+    pub fn path_cost(self) -> usize {
+        match self {
+            TileKind::Lava => 20 * ACTION_COST as usize,
+            kind => kind.move_cost() as usize,
+        }
+    }
+
+    /// Whether anyone can stand here, unharmed and without swimming.
+    pub fn is_safe(self) -> bool {
+        !matches!(self, TileKind::DeepWater | TileKind::Lava)
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
 pub struct Tile {
+    pub kind: TileKind,
+    /// Whether the tile blocks movement and sight right now: closed doors
+    /// do, open ones do not.
     pub blocked: bool,
     pub block_sight: bool,
     pub explored: bool,
 }
 
 impl Tile {
-    pub fn empty() -> Self {
+    pub fn new(kind: TileKind) -> Self {
         Tile {
-            blocked: false,
-            block_sight: false,
+            kind: kind,
+            blocked: kind.blocks_motion(),
+            block_sight: kind.blocks_sight(),
             explored: false,
         }
     }
 
+    pub fn empty() -> Self {
+        Tile::new(TileKind::Floor)
+    }
+
     pub fn wall() -> Self {
-        Tile {
-            blocked: true,
-            block_sight: true,
-            explored: false,
+        Tile::new(TileKind::Wall)
+    }
+
+    /// A closed door.
+    pub fn door() -> Self {
+        Tile::new(TileKind::Door)
+    }
+
+    pub fn is_closed_door(&self) -> bool {
+        self.kind == TileKind::Door && self.blocked
+    }
+
+    pub fn is_open_door(&self) -> bool {
+        self.kind == TileKind::Door && !self.blocked
+    }
+
+    pub fn set_open(&mut self, open: bool) {
+        self.blocked = !open;
+        self.block_sight = !open;
+    }
+
+    /// Whether something can step here, ignoring whatever stands on it.
+    pub fn walkable_by(&self, swims: bool) -> bool {
+        !self.blocked && (swims || self.kind != TileKind::DeepWater)
+    }
+
+    /// The character drawn over the background, if any.
+    pub fn glyph(&self) -> Option<(char, Color)> {
+        match self.kind {
+            TileKind::Door if self.blocked => Some(('+', COLOR_DOOR)),
+            TileKind::Door => Some(('\'', COLOR_DOOR)),
+            TileKind::Grass => Some(('"', DARK_GREEN)),
+            TileKind::Rubble => Some((':', GREY)),
+            TileKind::Water => Some(('~', LIGHT_SKY)),
+            TileKind::DeepWater => Some(('~', SKY)),
+            TileKind::Lava => Some(('~', YELLOW)),
+            TileKind::Floor | TileKind::Wall => None,
         }
     }
 }